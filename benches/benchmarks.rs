@@ -1,9 +1,10 @@
 use bitpart::{
-    metric::{Euclidean, Metric},
-    BitPart, Builder,
+    metric::{Counter, Counting, Euclidean, Metric},
+    BitPart, Builder, ColumnEncoding,
 };
 use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
 use rayon::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
 use sisap_data::{
     colors::{parse_colors, Colors},
     nasa::{parse_nasa, Nasa},
@@ -14,7 +15,7 @@ use std::{fs, time::Duration};
 /// Benchmark setup times for a particular dataset.
 fn setup_with<T>(c: &mut Criterion, group_name: String, builder: Builder<T>)
 where
-    for<'a> T: Metric + Send + Sync + 'a,
+    for<'a> T: Metric + Send + Sync + Serialize + DeserializeOwned + 'a,
 {
     let mut group = c.benchmark_group(group_name);
 
@@ -46,7 +47,7 @@ fn query_with<T>(
     query: T,
     threshold: f64,
 ) where
-    for<'a> T: Metric + Send + Sync + 'a,
+    for<'a> T: Metric + Send + Sync + Serialize + DeserializeOwned + 'a,
 {
     let mut group = c.benchmark_group(group_name);
 
@@ -233,9 +234,9 @@ fn nn_query_inner<T>(
     points: Vec<T>,
     thresholds: Vec<f64>,
 ) where
-    for<'a> T: Metric + Send + Sync + 'a,
+    for<'a> T: Metric + Send + Sync + Serialize + DeserializeOwned + 'a,
 {
-    let mut group = c.benchmark_group(group_name);
+    let mut group = c.benchmark_group(&group_name);
 
     // Benchmark a brute force search
     group.bench_function("lin", |bn| {
@@ -244,7 +245,7 @@ fn nn_query_inner<T>(
             |(points_inner, thresholds_inner)| {
                 for (query, threshold) in points_inner
                     .into_iter()
-                    .zip(thresholds_inner.into_iter())
+                    .zip(thresholds_inner)
                     .skip(skip_first)
                     .take(n)
                 {
@@ -266,7 +267,7 @@ fn nn_query_inner<T>(
             |(points_inner, thresholds_inner)| {
                 for (query, threshold) in points_inner
                     .into_iter()
-                    .zip(thresholds_inner.into_iter())
+                    .zip(thresholds_inner)
                     .skip(skip_first)
                     .take(n)
                 {
@@ -369,43 +370,151 @@ fn nn_query_inner<T>(
         });
     }
 
-    std::fs::remove_dir_all("/tmp/benchmark/").ok();
-    // Benchmark on-disk
-    let bitpart_disk = builder
-        .clone()
-        .build_on_disk("/tmp/benchmark/", Some(8192))
-        .unwrap();
-    group.bench_function("disk", |bn| {
-        bn.iter(|| {
-            for (query, threshold) in points
-                .iter()
-                .zip(thresholds.iter())
-                .skip(skip_first)
-                .take(n)
-            {
-                bitpart_disk
-                    .range_search(query.clone(), *threshold)
-                    .unwrap();
-            }
+    // Benchmark on-disk, comparing the size and speed of each column encoding
+    for (name, encoding) in [
+        ("disk", ColumnEncoding::Bitvec),
+        ("disk_roaring", ColumnEncoding::Roaring),
+    ] {
+        std::fs::remove_dir_all("/tmp/benchmark/").ok();
+        let bitpart_disk = builder
+            .clone()
+            .column_encoding(encoding)
+            .build_on_disk("/tmp/benchmark/", Some(8192))
+            .unwrap();
+        group.bench_function(name, |bn| {
+            bn.iter(|| {
+                for (query, threshold) in points
+                    .iter()
+                    .zip(thresholds.iter())
+                    .skip(skip_first)
+                    .take(n)
+                {
+                    bitpart_disk
+                        .range_search(query.clone(), *threshold)
+                        .unwrap();
+                }
+            });
         });
-    });
+    }
+    std::fs::remove_dir_all("/tmp/benchmark/").ok();
+}
+
+/// Prints the mean number of distance calculations made for each query by the variants benchmarked by [`nn_query_inner`], and
+/// the size of the columns of each on-disk encoding.
+///
+/// Distances are counted in a separate, untimed pass over copies of `points` which share a [`Counter`], so that the benchmarks
+/// themselves are not slowed down by counting.
+fn nn_distances<T>(group_name: &str, n: usize, skip_first: usize, points: &[T], thresholds: &[f64])
+where
+    for<'a> T: Metric + Send + Sync + Serialize + DeserializeOwned + 'a,
+{
+    let counter = Counter::new();
+    let points = points
+        .iter()
+        .map(|pt| Counting::new(pt.clone(), &counter))
+        .collect::<Vec<_>>();
+    let queries = points
+        .iter()
+        .cloned()
+        .zip(thresholds.iter().copied())
+        .skip(skip_first)
+        .take(n)
+        .collect::<Vec<_>>();
+    let builder = Builder::new(points.clone(), REF_POINTS as u64);
+
+    println!("{group_name}: distances per query");
+    println!("  lin: {}", points.len());
+    let bitpart = builder.clone().build();
+    println!(
+        "  seq: {:.0}",
+        count_distances(&bitpart, &queries, &counter)
+    );
+    let bitpart = builder.clone().build_parallel(Some(8192));
+    println!(
+        "  par: {:.0}",
+        count_distances(&bitpart, &queries, &counter)
+    );
+
+    for cull_threshold in [0.95, 0.9, 0.85, 0.8] {
+        let mut bitpart = builder.clone().build_parallel(Some(8192));
+        bitpart.cull_by_popcnt(cull_threshold);
+        println!(
+            "  cull_pop/{cull_threshold}: {:.0}",
+            count_distances(&bitpart, &queries, &counter)
+        );
+
+        let mut bitpart = builder.clone().build_parallel(Some(8192));
+        bitpart.cull_by_similarity(cull_threshold);
+        println!(
+            "  cull_sim/{cull_threshold}: {:.0}",
+            count_distances(&bitpart, &queries, &counter)
+        );
+
+        let mut bitpart = builder.clone().build_parallel(Some(8192));
+        bitpart.cull_by_popcnt(cull_threshold);
+        bitpart.cull_by_similarity(cull_threshold);
+        println!(
+            "  cull_all/{cull_threshold}: {:.0}",
+            count_distances(&bitpart, &queries, &counter)
+        );
+    }
+
+    for (name, encoding) in [
+        ("disk", ColumnEncoding::Bitvec),
+        ("disk_roaring", ColumnEncoding::Roaring),
+    ] {
+        std::fs::remove_dir_all("/tmp/benchmark/").ok();
+        let bitpart = builder
+            .clone()
+            .column_encoding(encoding)
+            .build_on_disk("/tmp/benchmark/", Some(8192))
+            .unwrap();
+        println!(
+            "  {name}: {:.0}, columns take {} bytes",
+            count_distances(&bitpart, &queries, &counter),
+            column_bytes("/tmp/benchmark/")
+        );
+    }
     std::fs::remove_dir_all("/tmp/benchmark/").ok();
 }
 
+/// Mean number of distance calculations made by `bitpart` for each of `queries`, counted with `counter`.
+fn count_distances<T, B>(bitpart: &B, queries: &[(T, f64)], counter: &Counter) -> f64
+where
+    T: Clone,
+    B: BitPart<T>,
+{
+    counter.reset();
+    for (query, threshold) in queries {
+        bitpart.range_search(query.clone(), *threshold).unwrap();
+    }
+    counter.reset() as f64 / queries.len() as f64
+}
+
+/// Total size of the bitset columns written by an on-disk BitPart in `path`.
+fn column_bytes(path: &str) -> u64 {
+    fs::read_dir(path)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| stem.parse::<usize>().is_ok())
+        })
+        .map(|path| fs::metadata(path).unwrap().len())
+        .sum()
+}
+
 /// Benchmark the average performance of various algorithms over different dimension sizes
 pub fn nn_query(c: &mut Criterion) {
     for dims in (10..=30).step_by(2) {
         let (points, thresholds) = get_nn_points(dims);
         let builder = Builder::new(points.clone(), REF_POINTS as u64);
+        let group_name = format!("100k_d{dims}_flat");
 
+        nn_distances(&group_name, NN_QUERIES, REF_POINTS, &points, &thresholds);
         nn_query_inner(
-            c,
-            format!("100k_d{dims}_flat"),
-            NN_QUERIES,
-            REF_POINTS,
-            builder,
-            points,
-            thresholds,
+            c, group_name, NN_QUERIES, REF_POINTS, builder, points, thresholds,
         );
     }
 }
@@ -417,7 +526,7 @@ fn block_size_inner<T>(
     points: Vec<T>,
     thresholds: Vec<f64>,
 ) where
-    for<'a> T: Metric + Send + Sync + 'a,
+    for<'a> T: Metric + Send + Sync + Serialize + DeserializeOwned + 'a,
 {
     let mut group = c.benchmark_group(group_name);
 
@@ -484,7 +593,7 @@ pub fn nn_setup_time(c: &mut Criterion) {
     let mut group = c.benchmark_group("setup");
 
     for sz in (1..=10).map(|x| x * 10000) {
-        let points_subset = points.iter().cloned().take(sz).collect::<Vec<_>>();
+        let points_subset = points.iter().take(sz).cloned().collect::<Vec<_>>();
         let builder = Builder::new(points_subset, 40);
 
         group.bench_function(BenchmarkId::new("seq", sz), |bn| {
@@ -36,6 +36,33 @@ impl<'a> IntoIterator for &'a Colors {
     }
 }
 
+// serde only implements traits for arrays of up to 32 elements, so points are (de)serialized as sequences instead.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Colors {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.as_slice().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Colors {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let values = Vec::<f64>::deserialize(deserializer)?;
+        let len = values.len();
+
+        values
+            .try_into()
+            .map(Colors)
+            .map_err(|_| serde::de::Error::invalid_length(len, &"112 values"))
+    }
+}
+
 /// Convenience function to parse `colors.ascii` and wrap points in [`Colors`].
 pub fn parse_colors(input: &str) -> Result<Vec<Colors>, nom::error::Error<&str>> {
     let (_, (_, v)) = parse_array(input).finish()?;
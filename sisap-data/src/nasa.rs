@@ -8,6 +8,7 @@ pub const NASA_DIMENSION: usize = 20;
 
 /// A data point in the NASA test dataset.
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Nasa(pub [f64; NASA_DIMENSION]);
 
 impl Deref for Nasa {
@@ -1,15 +1,166 @@
-use crate::{metric::Metric, Sequential};
+use crate::{
+    cull,
+    exclusions::{BallExclusion, Exclusion, HilbertExclusion, SheetExclusion, Zone},
+    metric::Metric,
+    Sequential,
+};
+
+use bitvec_simd::BitVec;
+use itertools::Itertools;
+use rand::{rngs::StdRng, SeedableRng};
+use std::{fmt, path::PathBuf, sync::Arc};
+use thiserror::Error;
 
 /// Builder for the BitPart data structure.
-#[derive(Debug, Clone)]
-pub struct Builder<T> {
+///
+/// Custom exclusion zones are `Send` and `Sync` by default, so every BitPart variant can be built. Once a zone which is not
+/// is added with [`local_exclusion`](Builder::local_exclusion), `E` becomes `dyn Exclusion<T>` and only a [`Sequential`] can be built.
+pub struct Builder<T, E: ?Sized = dyn Exclusion<T> + Send + Sync> {
     pub(crate) dataset: Vec<T>,
 
     pub(crate) radius_increment: f64,
     pub(crate) mean_distance: f64,
+    pub(crate) radii: Radii,
+    pub(crate) radii_per_point: usize,
+    pub(crate) offset: Offset,
+    pub(crate) sample_size: usize,
 
     pub(crate) four_point: bool,
     pub(crate) ref_points: u64,
+    pub(crate) selection: Selection,
+
+    pub(crate) generate_exclusions: bool,
+    pub(crate) custom_exclusions: Vec<Arc<E>>,
+
+    pub(crate) cull_popcnt: Option<f64>,
+    pub(crate) cull_similarity: Option<f64>,
+    pub(crate) cull_independent: Option<usize>,
+
+    #[cfg(feature = "disk")]
+    pub(crate) map_dataset: bool,
+    #[cfg(feature = "disk")]
+    pub(crate) column_encoding: crate::ColumnEncoding,
+    #[cfg(feature = "disk")]
+    pub(crate) container: bool,
+}
+
+impl<T, E> Clone for Builder<T, E>
+where
+    T: Clone,
+    E: ?Sized,
+{
+    fn clone(&self) -> Self {
+        Self {
+            dataset: self.dataset.clone(),
+            radius_increment: self.radius_increment,
+            mean_distance: self.mean_distance,
+            radii: self.radii,
+            radii_per_point: self.radii_per_point,
+            offset: self.offset,
+            sample_size: self.sample_size,
+            four_point: self.four_point,
+            ref_points: self.ref_points,
+            selection: self.selection,
+            generate_exclusions: self.generate_exclusions,
+            custom_exclusions: self.custom_exclusions.clone(),
+            cull_popcnt: self.cull_popcnt,
+            cull_similarity: self.cull_similarity,
+            cull_independent: self.cull_independent,
+            #[cfg(feature = "disk")]
+            map_dataset: self.map_dataset,
+            #[cfg(feature = "disk")]
+            column_encoding: self.column_encoding,
+            #[cfg(feature = "disk")]
+            container: self.container,
+        }
+    }
+}
+
+impl<T, E> fmt::Debug for Builder<T, E>
+where
+    T: fmt::Debug,
+    E: ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("Builder");
+        f.field("dataset", &self.dataset)
+            .field("radius_increment", &self.radius_increment)
+            .field("mean_distance", &self.mean_distance)
+            .field("radii", &self.radii)
+            .field("radii_per_point", &self.radii_per_point)
+            .field("offset", &self.offset)
+            .field("sample_size", &self.sample_size)
+            .field("four_point", &self.four_point)
+            .field("ref_points", &self.ref_points)
+            .field("selection", &self.selection)
+            .field("generate_exclusions", &self.generate_exclusions)
+            .field("custom_exclusions", &self.custom_exclusions.len())
+            .field("cull_popcnt", &self.cull_popcnt)
+            .field("cull_similarity", &self.cull_similarity)
+            .field("cull_independent", &self.cull_independent);
+        #[cfg(feature = "disk")]
+        f.field("map_dataset", &self.map_dataset)
+            .field("column_encoding", &self.column_encoding)
+            .field("container", &self.container);
+        f.finish()
+    }
+}
+
+/// Strategy used to select reference points from the dataset.
+///
+/// Exclusion zones are defined in terms of reference points, so a poor selection (for example, points which are all clustered together)
+/// results in exclusion zones which do not partition the dataset well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Selection {
+    /// Use the first `ref_points` points of the dataset.
+    ///
+    /// This is the default, and is only a good choice if the dataset is already in a random order.
+    First,
+    /// Choose reference points uniformly at random, without replacement.
+    ///
+    /// The same `seed` will always select the same reference points from the same dataset.
+    Random {
+        /// Seed for the random number generator.
+        seed: u64,
+    },
+    /// Choose reference points by [farthest-first traversal](https://en.wikipedia.org/wiki/Farthest-first_traversal).
+    ///
+    /// Starting from the first point in the dataset, the point farthest from all previously selected reference points is chosen next.
+    /// This spreads reference points across the dataset, at the cost of `ref_points * dataset.len()` distance calculations.
+    FarthestFirst,
+}
+
+/// Strategy used to choose the radii of ball exclusion zones.
+///
+/// Each reference point is the centre of [`radii_per_point`](Builder::radii_per_point) ball exclusion zones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Radii {
+    /// Radii are evenly spaced [`radius_increment`](Builder::radius_increment) apart, centred on [`mean_distance`](Builder::mean_distance).
+    ///
+    /// This is the default, and is only a good choice if those values have been tuned for the dataset.
+    Fixed,
+    /// Radii are chosen at evenly spaced quantiles of the distances between every reference point and a sample of the dataset.
+    /// Every reference point uses the same radii.
+    Global,
+    /// Radii are chosen at evenly spaced quantiles of the distances between each reference point and a sample of the dataset.
+    /// Every reference point uses its own radii.
+    PerPoint,
+}
+
+/// Strategy used to choose the offset of sheet exclusion zones.
+///
+/// A sheet exclusion zone between reference points `a` and `b` contains every point `x` where `d(a, x) - d(b, x) < offset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Offset {
+    /// Every sheet has an offset of zero, placing it at the bisector of its two reference points.
+    ///
+    /// This is the default.
+    Zero,
+    /// Each sheet's offset is the median of `d(a, x) - d(b, x)` over a sample of the dataset, so that it splits the dataset roughly in half.
+    Median,
 }
 
 impl<T> Builder<T>
@@ -20,25 +171,133 @@ where
     ///
     /// # Panics
     /// This function will panic if `ref_points` is greater than the size of the dataset, or is zero.
+    /// See [`try_new`](Builder::try_new) for a non-panicking alternative.
     pub fn new(dataset: impl IntoIterator<Item = T>, ref_points: u64) -> Self {
+        Self::try_new(dataset, ref_points).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Create a new `BitPartBuilder` from a dataset, returning an error if the dataset is empty,
+    /// or if `ref_points` is greater than the size of the dataset or is zero.
+    pub fn try_new(
+        dataset: impl IntoIterator<Item = T>,
+        ref_points: u64,
+    ) -> Result<Self, BuildError> {
         let dataset = dataset.into_iter().collect::<Vec<_>>();
 
-        assert!(ref_points as usize <= dataset.len());
-        assert!(ref_points > 0);
+        if dataset.is_empty() {
+            return Err(BuildError::EmptyDataset);
+        }
 
-        Self {
+        let builder = Self {
             dataset,
             mean_distance: 1.81,
             radius_increment: 0.3,
-            four_point: true,
+            radii: Radii::Fixed,
+            radii_per_point: 5,
+            offset: Offset::Zero,
+            sample_size: 1000,
+            four_point: T::SUPERMETRIC.is_some(),
             ref_points,
+            selection: Selection::First,
+            generate_exclusions: true,
+            custom_exclusions: vec![],
+            cull_popcnt: None,
+            cull_similarity: None,
+            cull_independent: None,
+            #[cfg(feature = "disk")]
+            map_dataset: false,
+            #[cfg(feature = "disk")]
+            column_encoding: crate::ColumnEncoding::default(),
+            #[cfg(feature = "disk")]
+            container: false,
+        };
+
+        builder.validate()?;
+        Ok(builder)
+    }
+
+    /// Add a custom exclusion zone.
+    ///
+    /// Custom exclusion zones are used alongside any generated ball and sheet exclusion zones, and are ordered after them.
+    /// They must be `Send` and `Sync` so that they can be shared with [`Parallel`](crate::Parallel) and [`Disk`](crate::Disk);
+    /// use [`local_exclusion`](Builder::local_exclusion) for zones which are not.
+    ///
+    /// **It is the responsibility of the implementer to ensure that the exclusion zone is correct.** See [`Exclusion`] for details.
+    pub fn exclusion<Z>(mut self, exclusion: Z) -> Self
+    where
+        Z: Exclusion<T> + Send + Sync + 'static,
+    {
+        self.custom_exclusions.push(Arc::new(exclusion));
+        self
+    }
+
+    /// Add a custom exclusion zone which need not be `Send` or `Sync`, such as a `Box<dyn Exclusion<T>>`.
+    ///
+    /// The returned builder can only build a [`Sequential`], which does not share its zones between threads.
+    /// Otherwise, this is identical to [`exclusion`](Builder::exclusion).
+    pub fn local_exclusion<Z>(self, exclusion: Z) -> Builder<T, dyn Exclusion<T>>
+    where
+        Z: Exclusion<T> + 'static,
+    {
+        let custom_exclusions = self
+            .custom_exclusions
+            .iter()
+            .map(|ez| ez.clone() as Arc<dyn Exclusion<T>>)
+            .collect();
+
+        Builder {
+            dataset: self.dataset,
+            radius_increment: self.radius_increment,
+            mean_distance: self.mean_distance,
+            radii: self.radii,
+            radii_per_point: self.radii_per_point,
+            offset: self.offset,
+            sample_size: self.sample_size,
+            four_point: self.four_point,
+            ref_points: self.ref_points,
+            selection: self.selection,
+            generate_exclusions: self.generate_exclusions,
+            custom_exclusions,
+            cull_popcnt: self.cull_popcnt,
+            cull_similarity: self.cull_similarity,
+            cull_independent: self.cull_independent,
+            #[cfg(feature = "disk")]
+            map_dataset: self.map_dataset,
+            #[cfg(feature = "disk")]
+            column_encoding: self.column_encoding,
+            #[cfg(feature = "disk")]
+            container: self.container,
         }
+        .local_exclusion(exclusion)
     }
+}
 
-    /// Set the mean distance used when generating ball exclusion zones.
+impl<T> Builder<T, dyn Exclusion<T>>
+where
+    for<'a> T: Metric + 'a,
+{
+    /// Add a custom exclusion zone which need not be `Send` or `Sync`.
+    ///
+    /// Otherwise, this is identical to [`exclusion`](Builder::exclusion).
+    pub fn local_exclusion<Z>(mut self, exclusion: Z) -> Self
+    where
+        Z: Exclusion<T> + 'static,
+    {
+        self.custom_exclusions.push(Arc::new(exclusion));
+        self
+    }
+}
+
+impl<T, E> Builder<T, E>
+where
+    for<'a> T: Metric + 'a,
+    E: Exclusion<T> + ?Sized,
+{
+    /// Set the mean distance used when generating ball exclusion zones with [`Radii::Fixed`].
     /// For historical reasons, the default value is `1.81`.
     ///
-    /// To generate ball exclusions, five radii are chosen per reference point. Their distances are:
+    /// Radii are evenly spaced `radius_increment` apart, centred on `mean_distance`. For example, with the default of five radii
+    /// per reference point, their distances are:
     /// ```text
     /// mean_distance + 2 * radius_increment
     /// mean_distance + radius_increment
@@ -51,7 +310,7 @@ where
         self
     }
 
-    /// Set the radius incremented used when generating exclusion zones.
+    /// Set the radius incremented used when generating exclusion zones with [`Radii::Fixed`].
     /// For historical reasons, the default value is `0.3`.
     ///
     /// See [`mean_distance`](crate::Builder::mean_distance) for a detailed explanation of how this value is used.
@@ -60,27 +319,621 @@ where
         self
     }
 
+    /// Set the strategy used to choose the radii of ball exclusion zones.
+    /// The default value is [`Radii::Fixed`].
+    ///
+    /// With [`Radii::Global`] or [`Radii::PerPoint`], the `n` radii are placed at the `1/(n+1), 2/(n+1), ..., n/(n+1)` quantiles
+    /// of the sampled distances. When `n` is odd, the middle radius is the median, so that ball splits the dataset roughly in half.
+    pub fn radii(mut self, radii: Radii) -> Self {
+        self.radii = radii;
+        self
+    }
+
+    /// Set the number of ball exclusion zones generated for each reference point.
+    /// The default value is `5`.
+    pub fn radii_per_point(mut self, radii_per_point: usize) -> Self {
+        self.radii_per_point = radii_per_point;
+        self
+    }
+
+    /// Set the strategy used to choose the offset of sheet exclusion zones.
+    /// The default value is [`Offset::Zero`].
+    pub fn offset(mut self, offset: Offset) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Set the number of points sampled from the dataset when choosing parameters for exclusion zones.
+    /// The default value is `1000`.
+    ///
+    /// Points are sampled at evenly spaced indices throughout the dataset, so the same dataset always produces the same sample.
+    /// `sample_size` must be greater than zero, or building the BitPart will fail.
+    pub fn sample_size(mut self, sample_size: usize) -> Self {
+        self.sample_size = sample_size;
+        self
+    }
+
+    /// Set the number of ref points.
+    ///
+    /// `ref_points` must be greater than zero and no greater than the size of the dataset, or building the BitPart will fail.
+    pub fn ref_points(mut self, ref_points: u64) -> Self {
+        self.ref_points = ref_points;
+        self
+    }
+
     /// Set whether to use four-point or three-point method for sheet exclusions.
-    #[deprecated(note = "This option does nothing.")]
+    /// The default value is `true` for [supermetric](crate::metric::SuperMetric) spaces, and `false` otherwise.
+    ///
+    /// The four-point method uses the four-point property of supermetric spaces to exclude a query which is within `threshold` of a sheet,
+    /// rather than within `2 * threshold` as in the three-point method. It is only valid for types which set
+    /// [`Metric::SUPERMETRIC`](crate::metric::Metric::SUPERMETRIC); for any other type, the three-point method is always used.
     pub fn four_point(mut self, four_point: bool) -> Self {
-        self.four_point = four_point;
+        self.four_point = four_point && T::SUPERMETRIC.is_some();
         self
     }
 
-    /// Set the number of ref points
+    /// Set the strategy used to select reference points.
+    /// The default value is [`Selection::First`].
+    pub fn selection(mut self, selection: Selection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Set whether ball and sheet exclusion zones are generated from reference points.
+    /// The default value is `true`.
     ///
-    /// # Panics
-    /// This function will panic if `ref_points` is greater than the size of the dataset, or is zero.
-    pub fn ref_points(mut self, ref_points: u64) -> Self {
-        assert!(ref_points as usize <= self.dataset.len());
-        assert!(ref_points > 0);
+    /// Disabling generated exclusion zones is only useful in combination with [`exclusion`](Builder::exclusion).
+    pub fn generate_exclusions(mut self, generate_exclusions: bool) -> Self {
+        self.generate_exclusions = generate_exclusions;
+        self
+    }
 
-        self.ref_points = ref_points;
+    /// Cull exclusion zones with low exclusion power before their partitioning data is built, so they are never stored.
+    /// By default, no zones are culled.
+    ///
+    /// A zone is culled if the ratio of points in it, or out of it, to the size of the dataset is above `threshold`.
+    /// See [`Sequential::cull_by_popcnt`] for culling after the data structure is built.
+    pub fn cull_by_popcnt(mut self, threshold: f64) -> Self {
+        self.cull_popcnt = Some(threshold);
+        self
+    }
+
+    /// Cull exclusion zones which partition the dataset similarly to another zone, before their partitioning data is built.
+    /// By default, no zones are culled.
+    ///
+    /// A zone is culled if its similarity ratio to an earlier zone is above `threshold`. If [`cull_by_popcnt`](Builder::cull_by_popcnt)
+    /// is also set, it is applied first. The columns of every remaining zone are held in memory while they are compared.
+    /// See [`Sequential::cull_by_similarity`] for culling after the data structure is built.
+    pub fn cull_by_similarity(mut self, threshold: f64) -> Self {
+        self.cull_similarity = Some(threshold);
+        self
+    }
+
+    /// Cull every exclusion zone except the `keep` most independent zones before their partitioning data is built.
+    /// By default, no zones are culled.
+    ///
+    /// Zones are chosen greedily: first the zone which splits the dataset most evenly, and then repeatedly the zone whose column is farthest,
+    /// by [Hamming distance](https://en.wikipedia.org/wiki/Hamming_distance), from the column of its nearest chosen zone. A zone and its
+    /// complement partition the dataset identically, so are treated as equal. This is applied after
+    /// [`cull_by_popcnt`](Builder::cull_by_popcnt) and [`cull_by_similarity`](Builder::cull_by_similarity), if they are set.
+    /// See [`Sequential::cull_to_independent`] for culling after the data structure is built.
+    pub fn cull_to_independent(mut self, keep: usize) -> Self {
+        self.cull_independent = Some(keep);
         self
     }
 
     /// Build the BitPart.
-    pub fn build<'a>(self) -> Sequential<'a, T> {
-        Sequential::setup(self)
+    ///
+    /// # Panics
+    /// This function will panic if the builder's parameters are invalid.
+    /// See [`try_build`](Builder::try_build) for a non-panicking alternative.
+    pub fn build(self) -> Sequential<T, E> {
+        self.try_build().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Build the BitPart, returning an error if the builder's parameters are invalid.
+    pub fn try_build(self) -> Result<Sequential<T, E>, BuildError> {
+        self.validate()?;
+        Ok(Sequential::setup(self))
+    }
+}
+
+impl<T, E> Builder<T, E>
+where
+    T: Metric,
+    E: Exclusion<T> + ?Sized,
+{
+    /// Returns every exclusion zone: generated ball and sheet exclusion zones, followed by custom exclusion zones.
+    /// Zones are culled according to [`cull_by_popcnt`](Builder::cull_by_popcnt), [`cull_by_similarity`](Builder::cull_by_similarity)
+    /// and [`cull_to_independent`](Builder::cull_to_independent).
+    pub(crate) fn zones(&self) -> Vec<Zone<T, E>> {
+        let mut zones = vec![];
+
+        if self.generate_exclusions {
+            let ref_points = self.select_ref_points();
+
+            zones.extend(
+                ref_points
+                    .iter()
+                    .zip(self.ball_radii(&ref_points))
+                    .flat_map(|(point, radii)| {
+                        radii
+                            .into_iter()
+                            .map(|radius| Zone::Ball(BallExclusion::new(point.clone(), radius)))
+                    }),
+            );
+
+            zones.extend(ref_points.iter().combinations(2).map(|x| {
+                // A Hilbert exclusion divides by the distance between its points, so duplicate reference points get a sheet
+                if self.four_point && x[0].distance(x[1]) > 0.0 {
+                    let mut ez = HilbertExclusion::new(x[0].clone(), x[1].clone(), 0.0);
+                    ez.set_offset(self.sheet_offset(|pt| ez.position(pt)));
+                    Zone::Hilbert(ez)
+                } else {
+                    let mut ez = SheetExclusion::new(x[0].clone(), x[1].clone(), 0.0);
+                    ez.set_offset(self.sheet_offset(|pt| ez.position(pt)));
+                    Zone::Sheet(ez)
+                }
+            }));
+        }
+
+        zones.extend(
+            self.custom_exclusions
+                .iter()
+                .map(|ez| Zone::Custom(ez.clone())),
+        );
+
+        self.cull(zones)
+    }
+
+    /// Removes zones with low exclusion power from `zones`.
+    fn cull(&self, mut zones: Vec<Zone<T, E>>) -> Vec<Zone<T, E>> {
+        let len = self.dataset.len();
+
+        if let Some(threshold) = self.cull_popcnt {
+            let popcnts = zones
+                .iter()
+                .map(|ez| self.dataset.iter().filter(|pt| ez.is_in(pt)).count());
+            let to_cull = cull::by_popcnt(popcnts, len, threshold);
+            cull::retain(&mut zones, &to_cull);
+        }
+
+        if self.cull_similarity.is_none() && self.cull_independent.is_none() {
+            return zones;
+        }
+
+        let mut columns = zones
+            .iter()
+            .map(|ez| BitVec::from_bool_iterator(self.dataset.iter().map(|pt| ez.is_in(pt))))
+            .collect::<Vec<_>>();
+
+        if let Some(threshold) = self.cull_similarity {
+            let to_cull = cull::by_similarity(
+                &cull::Columns::single(columns.iter().map(cull::words), len),
+                threshold,
+            );
+            cull::retain(&mut zones, &to_cull);
+            cull::retain(&mut columns, &to_cull);
+        }
+
+        if let Some(keep) = self.cull_independent {
+            let to_cull = cull::to_independent(
+                &cull::Columns::single(columns.iter().map(cull::words), len),
+                keep,
+            );
+            cull::retain(&mut zones, &to_cull);
+        }
+
+        zones
+    }
+
+    /// Selects reference points from the dataset according to the builder's [`Selection`] strategy.
+    pub(crate) fn select_ref_points(&self) -> Vec<T> {
+        let count = self.ref_points as usize;
+
+        match self.selection {
+            Selection::First => self.dataset[0..count].to_vec(),
+            Selection::Random { seed } => {
+                let mut rng = StdRng::seed_from_u64(seed);
+                rand::seq::index::sample(&mut rng, self.dataset.len(), count)
+                    .into_iter()
+                    .map(|idx| self.dataset[idx].clone())
+                    .collect()
+            }
+            Selection::FarthestFirst => {
+                let mut ref_points = vec![self.dataset[0].clone()];
+
+                // Distance from each point to its closest reference point
+                let mut distances = self
+                    .dataset
+                    .iter()
+                    .map(|pt| pt.distance(&ref_points[0]))
+                    .collect::<Vec<_>>();
+
+                while ref_points.len() < count {
+                    let (farthest, _) = distances
+                        .iter()
+                        .enumerate()
+                        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                        .unwrap();
+                    let next = self.dataset[farthest].clone();
+
+                    for (dist, pt) in distances.iter_mut().zip(&self.dataset) {
+                        *dist = dist.min(pt.distance(&next));
+                    }
+
+                    ref_points.push(next);
+                }
+
+                ref_points
+            }
+        }
+    }
+}
+
+impl<T, E> Builder<T, E>
+where
+    T: Metric,
+    E: ?Sized,
+{
+    /// Returns a sample of at most `sample_size` points, evenly spaced throughout the dataset.
+    pub(crate) fn sample(&self) -> impl Iterator<Item = &T> {
+        let step = (self.dataset.len() / self.sample_size.max(1)).max(1);
+        self.dataset.iter().step_by(step).take(self.sample_size)
+    }
+
+    /// Returns the radii of ball exclusion zones for each reference point, according to the builder's [`Radii`] strategy.
+    pub(crate) fn ball_radii(&self, ref_points: &[T]) -> Vec<Vec<f64>> {
+        let n = self.radii_per_point;
+
+        match self.radii {
+            Radii::Fixed => vec![self.fixed_radii(); ref_points.len()],
+            Radii::Global => {
+                let distances = ref_points
+                    .iter()
+                    .flat_map(|rp| self.sample().map(|pt| rp.distance(pt)))
+                    .collect();
+
+                vec![quantiles(distances, n); ref_points.len()]
+            }
+            Radii::PerPoint => ref_points
+                .iter()
+                .map(|rp| quantiles(self.sample().map(|pt| rp.distance(pt)).collect(), n))
+                .collect(),
+        }
+    }
+
+    /// Checks that the builder's parameters will produce valid exclusion zones.
+    pub(crate) fn validate(&self) -> Result<(), BuildError> {
+        if self.ref_points == 0 {
+            return Err(BuildError::ZeroRefPoints);
+        }
+        if self.ref_points as usize > self.dataset.len() {
+            return Err(BuildError::TooManyRefPoints {
+                ref_points: self.ref_points,
+                len: self.dataset.len(),
+            });
+        }
+        if self.sample_size == 0 {
+            return Err(BuildError::ZeroSampleSize);
+        }
+
+        // Fixed radii are the only ones which do not come from distances in the dataset, so can be negative
+        if self.generate_exclusions && self.radii == Radii::Fixed {
+            if let Some(radius) = self.fixed_radii().into_iter().find(|radius| *radius < 0.0) {
+                return Err(BuildError::NegativeRadius(radius));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the radii of ball exclusion zones for [`Radii::Fixed`].
+    fn fixed_radii(&self) -> Vec<f64> {
+        let n = self.radii_per_point;
+
+        (0..n)
+            .map(|i| i as f64 - (n as f64 - 1.0) / 2.0)
+            .map(|i| self.mean_distance + i * self.radius_increment)
+            .collect()
+    }
+
+    /// Returns the offset of a sheet exclusion zone according to the builder's [`Offset`] strategy, where `position` gives the
+    /// signed position of a point relative to the sheet.
+    pub(crate) fn sheet_offset(&self, position: impl Fn(&T) -> f64) -> f64 {
+        match self.offset {
+            Offset::Zero => 0.0,
+            Offset::Median => quantiles(self.sample().map(position).collect(), 1)
+                .first()
+                .copied()
+                .unwrap_or(0.0),
+        }
+    }
+}
+
+/// Errors that can be encountered while building a BitPart data structure.
+#[derive(Debug, Error)]
+pub enum BuildError {
+    /// The dataset contains no points.
+    #[error("dataset is empty")]
+    EmptyDataset,
+    /// More reference points were requested than there are points in the dataset.
+    #[error("{ref_points} reference points requested, but the dataset only contains {len} points")]
+    TooManyRefPoints {
+        /// The number of reference points requested.
+        ref_points: u64,
+        /// The number of points in the dataset.
+        len: usize,
+    },
+    /// Zero reference points were requested.
+    #[error("at least one reference point is required")]
+    ZeroRefPoints,
+    /// A [`sample_size`](Builder::sample_size) of zero was requested.
+    #[error("sample size must be greater than zero")]
+    ZeroSampleSize,
+    /// A block size of `Some(0)` was requested. Use `None` to disable parallelism during queries.
+    #[error("block size must be greater than zero")]
+    ZeroBlockSize,
+    /// [`Radii::Fixed`] would generate a ball exclusion zone with a negative radius.
+    #[error("ball exclusion zone radius {0} is negative")]
+    NegativeRadius(f64),
+    /// The directory or container for an on-disk BitPart already exists.
+    #[error("{0} already exists")]
+    DirectoryExists(PathBuf),
+    /// Generic IO error. This means the directory for an on-disk BitPart could not be created.
+    #[error("io error")]
+    Io(#[from] std::io::Error),
+    /// Partitioning data could not be written to disk.
+    #[cfg(feature = "disk")]
+    #[error("could not write partitioning data")]
+    Disk(#[from] crate::DiskError),
+}
+
+/// Returns `n` evenly spaced quantiles of `values`, excluding the minimum and maximum.
+pub(crate) fn quantiles(mut values: Vec<f64>, n: usize) -> Vec<f64> {
+    if values.is_empty() {
+        return vec![];
+    }
+
+    values.sort_by(f64::total_cmp);
+
+    (1..=n)
+        .map(|i| i as f64 / (n + 1) as f64)
+        .map(|q| values[(q * (values.len() - 1) as f64).round() as usize])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{metric::Euclidean, BitPart};
+
+    fn points() -> Vec<Euclidean<[f64; 1]>> {
+        (0..=100).map(|x| Euclidean::new([x as f64])).collect()
+    }
+
+    #[test]
+    fn selection_first() {
+        let ref_points = Builder::new(points(), 3).select_ref_points();
+
+        assert_eq!(
+            ref_points.into_iter().map(|p| p[0]).collect::<Vec<_>>(),
+            [0.0, 1.0, 2.0]
+        );
+    }
+
+    #[test]
+    fn selection_random_seeded() {
+        let builder = Builder::new(points(), 10).selection(Selection::Random { seed: 42 });
+
+        let a = builder.select_ref_points();
+        let b = builder.select_ref_points();
+        let c = builder
+            .clone()
+            .selection(Selection::Random { seed: 43 })
+            .select_ref_points();
+
+        let a = a.into_iter().map(|p| p[0]).collect::<Vec<_>>();
+        let b = b.into_iter().map(|p| p[0]).collect::<Vec<_>>();
+        let c = c.into_iter().map(|p| p[0]).collect::<Vec<_>>();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        // Points are sampled without replacement
+        let mut deduped = a.clone();
+        deduped.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        deduped.dedup();
+        assert_eq!(deduped.len(), a.len());
+    }
+
+    #[test]
+    fn selection_farthest_first() {
+        let ref_points = Builder::new(points(), 3)
+            .selection(Selection::FarthestFirst)
+            .select_ref_points();
+
+        assert_eq!(
+            ref_points.into_iter().map(|p| p[0]).collect::<Vec<_>>(),
+            [0.0, 100.0, 50.0]
+        );
+    }
+
+    #[test]
+    fn radii_fixed() {
+        let radii = Builder::new(points(), 2)
+            .mean_distance(10.0)
+            .radius_increment(1.0)
+            .radii_per_point(4)
+            .ball_radii(&[Euclidean::new([0.0])]);
+
+        assert_eq!(radii, [[8.5, 9.5, 10.5, 11.5]]);
+    }
+
+    #[test]
+    fn radii_per_point() {
+        let ref_points = [Euclidean::new([0.0]), Euclidean::new([50.0])];
+        let radii = Builder::new(points(), 2)
+            .radii(Radii::PerPoint)
+            .radii_per_point(1)
+            .ball_radii(&ref_points);
+
+        assert_eq!(radii, [[50.0], [25.0]]);
+    }
+
+    #[test]
+    fn radii_global() {
+        let ref_points = [Euclidean::new([0.0]), Euclidean::new([50.0])];
+        let radii = Builder::new(points(), 2)
+            .radii(Radii::Global)
+            .radii_per_point(3)
+            .ball_radii(&ref_points);
+
+        assert_eq!(radii[0], radii[1]);
+        assert_eq!(radii[0].len(), 3);
+        assert!(radii[0].windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn offset_median() {
+        let a = Euclidean::new([0.0]);
+        let b = Euclidean::new([10.0]);
+
+        let position = |pt: &Euclidean<[f64; 1]>| a.distance(pt) - b.distance(pt);
+
+        let builder = Builder::new(points(), 2);
+        assert_eq!(builder.sheet_offset(position), 0.0);
+
+        // Points are in 0..=100, so d(a, x) - d(b, x) = -10 at x = 0 and 10 for x >= 10, with median 10
+        let builder = builder.offset(Offset::Median);
+        assert_eq!(builder.sheet_offset(position), 10.0);
+    }
+
+    #[test]
+    fn four_point_default() {
+        #[derive(Clone)]
+        struct Discrete(u8);
+
+        impl Metric for Discrete {
+            fn distance(&self, rhs: &Self) -> f64 {
+                if self.0 == rhs.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+        }
+
+        assert!(Builder::new(points(), 2).four_point);
+        assert!(!Builder::new((0..10).map(Discrete), 2).four_point);
+
+        // Four-point can be turned off for any metric, but only turned on for supermetrics
+        assert!(!Builder::new(points(), 2).four_point(false).four_point);
+        assert!(
+            !Builder::new((0..10).map(Discrete), 2)
+                .four_point(true)
+                .four_point
+        );
+    }
+
+    #[test]
+    fn duplicate_ref_points() {
+        let dataset = std::iter::once(0.0)
+            .chain((0..=100).map(|x| x as f64))
+            .map(|x| Euclidean::new([x]))
+            .collect::<Vec<_>>();
+        let builder = Builder::new(dataset.clone(), 3);
+
+        // Of the pairs of reference points 0, 0 and 1, only the two distinct pairs get Hilbert exclusions
+        let zones = builder.zones();
+        let count = |f: fn(&Zone<_>) -> bool| zones.iter().filter(|ez| f(ez)).count();
+        assert_eq!(count(|ez| matches!(ez, Zone::Sheet(_))), 1);
+        assert_eq!(count(|ez| matches!(ez, Zone::Hilbert(_))), 2);
+
+        let bitpart = builder.build();
+        let query = Euclidean::new([0.5]);
+        assert_eq!(
+            bitpart.range_search(query.clone(), 2.0).unwrap().len(),
+            dataset
+                .iter()
+                .filter(|pt| pt.distance(&query) <= 2.0)
+                .count()
+        );
+    }
+
+    #[test]
+    fn try_new_errors() {
+        let empty: Vec<Euclidean<[f64; 1]>> = vec![];
+        assert!(matches!(
+            Builder::try_new(empty, 1),
+            Err(BuildError::EmptyDataset)
+        ));
+        assert!(matches!(
+            Builder::try_new(points(), 0),
+            Err(BuildError::ZeroRefPoints)
+        ));
+        assert!(matches!(
+            Builder::try_new(points(), 102),
+            Err(BuildError::TooManyRefPoints {
+                ref_points: 102,
+                len: 101
+            })
+        ));
+        assert!(Builder::try_new(points(), 101).is_ok());
+    }
+
+    #[test]
+    fn try_build_ref_points() {
+        let builder = Builder::new(points(), 3);
+        assert!(matches!(
+            builder.clone().ref_points(0).try_build(),
+            Err(BuildError::ZeroRefPoints)
+        ));
+        assert!(matches!(
+            builder.clone().ref_points(102).try_build(),
+            Err(BuildError::TooManyRefPoints {
+                ref_points: 102,
+                len: 101
+            })
+        ));
+
+        // Checked even when radii are data-driven
+        assert!(matches!(
+            builder
+                .clone()
+                .radii(Radii::Global)
+                .ref_points(0)
+                .try_build(),
+            Err(BuildError::ZeroRefPoints)
+        ));
+        assert!(builder.ref_points(101).try_build().is_ok());
+    }
+
+    #[test]
+    fn try_build_zero_sample_size() {
+        assert!(matches!(
+            Builder::new(points(), 3).sample_size(0).try_build(),
+            Err(BuildError::ZeroSampleSize)
+        ));
+    }
+
+    #[test]
+    #[should_panic]
+    fn build_zero_ref_points() {
+        Builder::new(points(), 3).ref_points(0).build();
+    }
+
+    #[test]
+    fn try_build_negative_radius() {
+        let builder = Builder::new(points(), 3).mean_distance(0.5);
+        assert!(matches!(
+            builder.clone().try_build(),
+            Err(BuildError::NegativeRadius(_))
+        ));
+
+        // Data-driven radii are never negative
+        assert!(builder.clone().radii(Radii::Global).try_build().is_ok());
+        // Ball exclusion zones are not generated
+        assert!(builder.generate_exclusions(false).try_build().is_ok());
     }
 }
@@ -0,0 +1,122 @@
+//! Serialization of [`bitvec_simd`](bitvec_simd) bitset columns, for use with `#[serde(with = "crate::columns")]`.
+//!
+//! Each bitvector is written as its length followed by its bits packed into `u64` words.
+//! `bitvec_simd`'s own `use_serde` feature is not used, as it drops trailing zero words and
+//! produces bitvectors which panic when inverted.
+
+use bitvec_simd::BitVec;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Types made of bitset columns, which can be converted to and from packed words.
+pub(crate) trait Columns: Sized {
+    type Words: Serialize + DeserializeOwned;
+
+    fn to_words(&self) -> Self::Words;
+
+    /// Returns `None` if the number of words does not match the length of a bitvector, or bits past its length are set.
+    fn from_words(words: Self::Words) -> Option<Self>;
+}
+
+impl Columns for BitVec {
+    type Words = (usize, Vec<u64>);
+
+    fn to_words(&self) -> Self::Words {
+        let mut words = crate::cull::words(self).to_vec();
+        // Bits past the end of the bitvector are not guaranteed to be zero in memory
+        if let (Some(last), Some(mask)) = (words.last_mut(), tail_mask(self.len())) {
+            *last &= mask;
+        }
+
+        (self.len(), words)
+    }
+
+    fn from_words((len, words): Self::Words) -> Option<Self> {
+        if words.len() != len.div_ceil(64) {
+            return None;
+        }
+        // Reject set bits past the end of the bitvector, which would otherwise appear when it is inverted or counted
+        if let (Some(last), Some(mask)) = (words.last(), tail_mask(len)) {
+            if last & !mask != 0 {
+                return None;
+            }
+        }
+
+        Some(BitVec::from_slice_copy(&words, len))
+    }
+}
+
+/// Returns the mask of bits in the last word of a bitvector of `len` bits, or `None` if every bit of the last word is used.
+fn tail_mask(len: usize) -> Option<u64> {
+    match len % 64 {
+        0 => None,
+        rest => Some((1 << rest) - 1),
+    }
+}
+
+impl<C> Columns for Vec<C>
+where
+    C: Columns,
+{
+    type Words = Vec<C::Words>;
+
+    fn to_words(&self) -> Self::Words {
+        self.iter().map(C::to_words).collect()
+    }
+
+    fn from_words(words: Self::Words) -> Option<Self> {
+        words.into_iter().map(C::from_words).collect()
+    }
+}
+
+pub(crate) fn serialize<C, S>(columns: &C, serializer: S) -> Result<S::Ok, S::Error>
+where
+    C: Columns,
+    S: Serializer,
+{
+    columns.to_words().serialize(serializer)
+}
+
+pub(crate) fn deserialize<'de, C, D>(deserializer: D) -> Result<C, D::Error>
+where
+    C: Columns,
+    D: Deserializer<'de>,
+{
+    C::from_words(C::Words::deserialize(deserializer)?)
+        .ok_or_else(|| serde::de::Error::custom("bitvector words do not match its length"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        // Trailing zero words must survive, along with lengths which are not a multiple of 64
+        for len in [0, 1, 63, 64, 65, 1000, 1025] {
+            let bv = BitVec::from_bool_iterator((0..len).map(|idx| idx % 3 == 0 && idx < len / 2));
+            let words = bv.to_words();
+            let de = BitVec::from_words(words).unwrap();
+
+            assert_eq!(de.len(), len);
+            assert_eq!(de.clone().into_bools(), bv.clone().into_bools());
+            assert_eq!((!de).into_bools(), (!bv).into_bools());
+        }
+    }
+
+    #[test]
+    fn mismatched_words() {
+        assert!(BitVec::from_words((65, vec![0])).is_none());
+        // Bit 65 is past the end of the bitvector
+        assert!(BitVec::from_words((65, vec![0, 0b10])).is_none());
+        assert!(BitVec::from_words((65, vec![0, 0b1])).is_some());
+    }
+
+    #[test]
+    fn inverted_round_trip() {
+        // Inverting sets bits past the end of the bitvector in memory, which must not be serialized
+        let bv = !BitVec::zeros(65);
+        let (_, words) = bv.to_words();
+        assert_eq!(words, vec![u64::MAX, 1]);
+        assert_eq!(BitVec::from_words((65, words)).unwrap().count_ones(), 65);
+    }
+}
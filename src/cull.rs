@@ -0,0 +1,244 @@
+//! Selection of exclusion zones with low exclusion power, shared by every BitPart variant and by the [`Builder`](crate::Builder).
+//!
+//! Each function returns the indices of the zones to cull, which are removed from a list of zones or columns with [`retain`].
+
+use std::collections::HashSet;
+
+/// The partitioning data of every zone, as packed `u64` words.
+///
+/// Each column is split into the same blocks of points, such as the blocks of a [`Parallel`](crate::Parallel). Every block starts
+/// on a new word, and bits past the end of a block are ignored.
+pub(crate) struct Columns<'a> {
+    columns: Vec<Vec<&'a [u64]>>,
+    blocks: Vec<usize>,
+}
+
+impl<'a> Columns<'a> {
+    /// `columns` holds the words of each block of each column, and `blocks` the number of points in each block.
+    pub(crate) fn new(columns: Vec<Vec<&'a [u64]>>, blocks: Vec<usize>) -> Self {
+        Self { columns, blocks }
+    }
+
+    /// Columns of `len` points, each held in a single block.
+    pub(crate) fn single(columns: impl IntoIterator<Item = &'a [u64]>, len: usize) -> Self {
+        Self::new(
+            columns.into_iter().map(|words| vec![words]).collect(),
+            vec![len],
+        )
+    }
+
+    fn zones(&self) -> usize {
+        self.columns.len()
+    }
+
+    fn len(&self) -> usize {
+        self.blocks.iter().sum()
+    }
+
+    /// Counts the set bits of column `i`.
+    fn popcnt(&self, i: usize) -> usize {
+        self.columns[i]
+            .iter()
+            .zip(&self.blocks)
+            .map(|(words, bits)| count(words, *bits, |word, _| word))
+            .sum()
+    }
+
+    /// Returns the Hamming distance between columns `i` and `j`, or any distance at which `stop` is `true` once it is reached.
+    fn hamming(&self, i: usize, j: usize, stop: impl Fn(usize) -> bool) -> usize {
+        let mut dist = 0;
+        for ((a, b), bits) in self.columns[i]
+            .iter()
+            .zip(&self.columns[j])
+            .zip(&self.blocks)
+        {
+            dist += count(a, *bits, |word, idx| word ^ b[idx]);
+            if stop(dist) {
+                break;
+            }
+        }
+        dist
+    }
+}
+
+/// Counts the set bits of `f(word, idx)` over the first `bits` bits of `words`, without allocating.
+fn count(words: &[u64], bits: usize, f: impl Fn(u64, usize) -> u64) -> usize {
+    let full = bits / 64;
+    let ones = words[..full]
+        .iter()
+        .enumerate()
+        .map(|(idx, word)| f(*word, idx).count_ones() as usize)
+        .sum::<usize>();
+
+    match bits % 64 {
+        0 => ones,
+        rest => ones + (f(words[full], full) & ((1 << rest) - 1)).count_ones() as usize,
+    }
+}
+
+/// Returns the words of a [`bitvec_simd`] bitvector.
+pub(crate) fn words(bv: &bitvec_simd::BitVec) -> &[u64] {
+    // SAFETY: blocks are stored contiguously as `u64x4`, which is four `u64`s, and hold at least `len` bits
+    unsafe { std::slice::from_raw_parts(bv.as_ptr() as *const u64, bv.len().div_ceil(64)) }
+}
+
+/// Returns the zones whose ratio of points in, or out, to the `len` points of the dataset is above `threshold`.
+///
+/// `popcnts` holds the number of points in each zone.
+pub(crate) fn by_popcnt(
+    popcnts: impl IntoIterator<Item = usize>,
+    len: usize,
+    threshold: f64,
+) -> HashSet<usize> {
+    popcnts
+        .into_iter()
+        .enumerate()
+        .filter(|(_, cnt)| ratio(*cnt, len) > threshold || ratio(len - cnt, len) > threshold)
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+/// Returns every zone whose similarity ratio to an earlier zone is above `threshold`.
+///
+/// Zones are compared in parallel. Counting the [Hamming distance](https://en.wikipedia.org/wiki/Hamming_distance) between two
+/// columns stops as soon as they are known to be dissimilar, and a zone is not compared further once it is known to be culled.
+pub(crate) fn by_similarity(columns: &Columns, threshold: f64) -> HashSet<usize> {
+    let len = columns.len();
+    let similar = |hamming| 1.0 - ratio(hamming, len) > threshold;
+
+    map(columns.zones(), |j| {
+        (0..j).any(|i| similar(columns.hamming(i, j, |dist| !similar(dist))))
+    })
+    .into_iter()
+    .enumerate()
+    .filter(|(_, culled)| *culled)
+    .map(|(idx, _)| idx)
+    .collect()
+}
+
+/// Returns every zone except the `keep` most independent zones.
+///
+/// Zones are chosen greedily, starting from the zone which splits the dataset most evenly. Each following zone is the one
+/// farthest from its nearest chosen zone, where the distance between two zones is the smaller of the Hamming distance between
+/// their columns and the Hamming distance to the complement, since a zone and its complement partition the dataset identically.
+pub(crate) fn to_independent(columns: &Columns, keep: usize) -> HashSet<usize> {
+    let zones = columns.zones();
+    if keep == 0 {
+        return (0..zones).collect();
+    }
+
+    let len = columns.len();
+    let distance = |i, j| {
+        let hamming = columns.hamming(i, j, |_| false);
+        hamming.min(len - hamming)
+    };
+
+    let Some(mut next) = (0..zones).min_by_key(|i| columns.popcnt(*i).abs_diff(len / 2)) else {
+        return HashSet::new();
+    };
+    let mut kept = vec![false; zones];
+    let mut nearest = vec![usize::MAX; zones];
+
+    for _ in 0..keep.min(zones) {
+        kept[next] = true;
+        for (nearest, dist) in nearest.iter_mut().zip(map(zones, |i| distance(next, i))) {
+            *nearest = dist.min(*nearest);
+        }
+
+        // Ties go to the earliest zone
+        let farthest = (0..zones)
+            .filter(|idx| !kept[*idx])
+            .max_by_key(|idx| (nearest[*idx], std::cmp::Reverse(*idx)));
+        match farthest {
+            Some(idx) => next = idx,
+            None => break,
+        }
+    }
+
+    (0..zones).filter(|idx| !kept[*idx]).collect()
+}
+
+/// Removes the elements of `items` whose indices are in `to_cull`.
+pub(crate) fn retain<I>(items: &mut Vec<I>, to_cull: &HashSet<usize>) {
+    let mut idx = 0;
+    items.retain(|_| {
+        idx += 1;
+        !to_cull.contains(&(idx - 1))
+    });
+}
+
+fn ratio(count: usize, len: usize) -> f64 {
+    count as f64 / len as f64
+}
+
+/// Maps `f` over `0..n`, in parallel if the `par` feature is enabled.
+fn map<R: Send>(n: usize, f: impl Fn(usize) -> R + Send + Sync) -> Vec<R> {
+    #[cfg(feature = "par")]
+    {
+        use rayon::prelude::*;
+        (0..n).into_par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "par"))]
+    {
+        (0..n).map(f).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn popcnt() {
+        // Zones holding almost every point, or almost none, are culled
+        let to_cull = by_popcnt([0, 5, 50, 95, 100], 100, 0.9);
+        assert_eq!(to_cull, HashSet::from([0, 1, 3, 4]));
+    }
+
+    #[test]
+    fn similarity() {
+        let words = [[0b1100], [0b1101], [0b0011], [0b1100]];
+        let columns = Columns::single(words.iter().map(|w| w.as_slice()), 8);
+
+        // The later zone of each similar pair is culled
+        assert_eq!(by_similarity(&columns, 0.8), HashSet::from([1, 3]));
+        assert_eq!(by_similarity(&columns, 1.0), HashSet::new());
+    }
+
+    #[test]
+    fn hamming_blocks() {
+        // Bits past the end of each block are ignored
+        let a = [[u64::MAX, 0b01], [0b0110, 0]];
+        let b = [[0, u64::MAX], [0b1001, 0]];
+        let columns = Columns::new(
+            vec![
+                a.iter().map(|w| w.as_slice()).collect(),
+                b.iter().map(|w| w.as_slice()).collect(),
+            ],
+            vec![66, 3],
+        );
+
+        assert_eq!(columns.len(), 69);
+        assert_eq!(columns.popcnt(0), 65 + 2);
+        assert_eq!(columns.hamming(0, 1, |_| false), 64 + 1 + 3);
+        assert_eq!(columns.hamming(0, 1, |dist| dist > 10), 64 + 1);
+    }
+
+    #[test]
+    fn independent() {
+        // Zone 3 is the complement of zone 0, and zone 1 nearly a copy of zone 0
+        let words = [[0b00001111], [0b00011111], [0b00110011], [0b11110000]];
+        let columns = Columns::single(words.iter().map(|w| w.as_slice()), 8);
+
+        assert_eq!(to_independent(&columns, 2), HashSet::from([1, 3]));
+        assert_eq!(to_independent(&columns, 4), HashSet::new());
+        assert_eq!(to_independent(&columns, 0), HashSet::from([0, 1, 2, 3]));
+    }
+
+    #[test]
+    fn retain_indices() {
+        let mut items = vec!['a', 'b', 'c', 'd'];
+        retain(&mut items, &HashSet::from([0, 2]));
+        assert_eq!(items, vec!['b', 'd']);
+    }
+}
@@ -4,10 +4,20 @@
 //!
 //! # Default implementation
 //! The reference implementation uses both ball and sheet implementations, as described in the paper.
+//! For [supermetric](crate::metric::SuperMetric) spaces, sheets use the tighter four-point Hilbert exclusion by default;
+//! see [`Builder::four_point`](crate::Builder::four_point).
 //!
-//! While the Exclusion traits are not sealed, it is currently not possible to use custom exclusion zones in BitPart.
+//! # Custom exclusion zones
+//! Custom exclusion zones can be added with [`Builder::exclusion`](crate::Builder::exclusion), either alongside the generated zones
+//! or instead of them (see [`Builder::generate_exclusions`](crate::Builder::generate_exclusions)). Zones which are not `Send` and `Sync`
+//! can be added with [`Builder::local_exclusion`](crate::Builder::local_exclusion), but can then only be used by a [`Sequential`](crate::Sequential).
+//! Custom exclusion zones have no known definition, so a BitPart which uses them cannot be serialized or reopened with `Disk::open`.
 
 use crate::metric::Metric;
+use std::sync::Arc;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "par")]
 /// Marker trait for exclusions that are also `Send` and `Sync`.
@@ -18,6 +28,11 @@ where
 }
 
 /// An exclusion zone.
+///
+/// For search results to be correct, implementations must uphold the following for any query `point`, `threshold`, and any `x`
+/// where `point.distance(x) <= threshold`:
+/// 1. If `must_be_in(point, threshold)`, then `is_in(x)`.
+/// 2. If `must_be_out(point, threshold)`, then `!is_in(x)`.
 pub trait Exclusion<T>
 where
     T: Metric,
@@ -30,6 +45,59 @@ where
     fn must_be_out(&self, point: &T, threshold: f64) -> bool;
 }
 
+impl<T, E> Exclusion<T> for Arc<E>
+where
+    T: Metric,
+    E: Exclusion<T> + ?Sized,
+{
+    fn is_in(&self, point: &T) -> bool {
+        self.as_ref().is_in(point)
+    }
+
+    fn must_be_in(&self, point: &T, threshold: f64) -> bool {
+        self.as_ref().must_be_in(point, threshold)
+    }
+
+    fn must_be_out(&self, point: &T, threshold: f64) -> bool {
+        self.as_ref().must_be_out(point, threshold)
+    }
+}
+
+impl<T, E> Exclusion<T> for Box<E>
+where
+    T: Metric,
+    E: Exclusion<T> + ?Sized,
+{
+    fn is_in(&self, point: &T) -> bool {
+        self.as_ref().is_in(point)
+    }
+
+    fn must_be_in(&self, point: &T, threshold: f64) -> bool {
+        self.as_ref().must_be_in(point, threshold)
+    }
+
+    fn must_be_out(&self, point: &T, threshold: f64) -> bool {
+        self.as_ref().must_be_out(point, threshold)
+    }
+}
+
+#[cfg(feature = "par")]
+impl<T, E> ExclusionSync<T> for Box<E>
+where
+    T: Metric + Send + Sync,
+    E: Exclusion<T> + Send + Sync + ?Sized,
+{
+}
+
+#[cfg(feature = "par")]
+impl<T, E> ExclusionSync<T> for Arc<E>
+where
+    T: Metric + Send + Sync,
+    E: Exclusion<T> + Send + Sync + ?Sized,
+{
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub(crate) struct BallExclusion<T> {
     pub(crate) point: T,
     pub(crate) radius: f64,
@@ -49,21 +117,22 @@ where
     T: Metric,
 {
     fn is_in(&self, point: &T) -> bool {
-        self.point.distance(point) < self.radius
+        point.distance(&self.point) < self.radius
     }
 
     fn must_be_in(&self, point: &T, threshold: f64) -> bool {
-        self.point.distance(point) < (self.radius - threshold)
+        point.distance(&self.point) < (self.radius - threshold)
     }
 
     fn must_be_out(&self, point: &T, threshold: f64) -> bool {
-        self.point.distance(point) >= (self.radius + threshold)
+        point.distance(&self.point) >= (self.radius + threshold)
     }
 }
 
 #[cfg(feature = "par")]
 impl<T> ExclusionSync<T> for BallExclusion<T> where T: Metric + Send + Sync {}
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub(crate) struct SheetExclusion<T> {
     a: T,
     b: T,
@@ -77,6 +146,15 @@ where
     pub(crate) fn new(a: T, b: T, offset: f64) -> Self {
         Self { a, b, offset }
     }
+
+    /// Signed position of `point` relative to `a` and `b`, ignoring the offset.
+    pub(crate) fn position(&self, point: &T) -> f64 {
+        point.distance(&self.a) - point.distance(&self.b)
+    }
+
+    pub(crate) fn set_offset(&mut self, offset: f64) {
+        self.offset = offset;
+    }
 }
 
 impl<T> Exclusion<T> for SheetExclusion<T>
@@ -84,7 +162,7 @@ where
     T: Metric,
 {
     fn is_in(&self, point: &T) -> bool {
-        self.a.distance(point) - self.b.distance(point) - self.offset < 0.0
+        point.distance(&self.a) - point.distance(&self.b) - self.offset < 0.0
     }
 
     fn must_be_in(&self, point: &T, threshold: f64) -> bool {
@@ -98,3 +176,224 @@ where
 
 #[cfg(feature = "par")]
 impl<T> ExclusionSync<T> for SheetExclusion<T> where T: Metric + Send + Sync {}
+
+/// Sheet exclusion using the four-point property of [supermetric](crate::metric::SuperMetric) spaces.
+///
+/// Points are projected onto the line between reference points `a` and `b`, using the distance between them:
+/// ```text
+/// (d(a, x)^2 - d(b, x)^2) / (2 * d(a, b))
+/// ```
+/// In a supermetric space two points are never further apart on this line than they are in the space itself, so a query only needs
+/// to be `threshold` (rather than `2 * threshold`, as for [`SheetExclusion`]) from the sheet for it to be excluded.
+///
+/// **This exclusion zone gives incorrect results if the metric does not satisfy the four-point property.**
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct HilbertExclusion<T> {
+    a: T,
+    b: T,
+    distance: f64,
+    offset: f64,
+}
+
+impl<T> HilbertExclusion<T>
+where
+    T: Metric,
+{
+    pub(crate) fn new(a: T, b: T, offset: f64) -> Self {
+        let distance = a.distance(&b);
+        Self {
+            a,
+            b,
+            distance,
+            offset,
+        }
+    }
+
+    /// Signed position of `point` along the line between `a` and `b`, ignoring the offset.
+    pub(crate) fn position(&self, point: &T) -> f64 {
+        (point.distance(&self.a).powi(2) - point.distance(&self.b).powi(2)) / (2.0 * self.distance)
+    }
+
+    pub(crate) fn set_offset(&mut self, offset: f64) {
+        self.offset = offset;
+    }
+}
+
+impl<T> Exclusion<T> for HilbertExclusion<T>
+where
+    T: Metric,
+{
+    fn is_in(&self, point: &T) -> bool {
+        self.position(point) - self.offset < 0.0
+    }
+
+    fn must_be_in(&self, point: &T, threshold: f64) -> bool {
+        self.position(point) - self.offset < -threshold
+    }
+
+    fn must_be_out(&self, point: &T, threshold: f64) -> bool {
+        self.position(point) - self.offset >= threshold
+    }
+}
+
+#[cfg(feature = "par")]
+impl<T> ExclusionSync<T> for HilbertExclusion<T> where T: Metric + Send + Sync {}
+
+/// An exclusion zone, either generated by the [`Builder`](crate::Builder) or [custom](crate::Builder::exclusion).
+///
+/// Generated zones have a known definition, so can be (de)serialized. Custom zones cannot, and attempting to serialize them
+/// returns an error.
+///
+/// Custom zones are `Send` and `Sync` unless they were added with [`Builder::local_exclusion`](crate::Builder::local_exclusion),
+/// in which case `E` is `dyn Exclusion<T>`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>"))
+)]
+pub(crate) enum Zone<T, E: ?Sized = dyn Exclusion<T> + Send + Sync> {
+    Ball(BallExclusion<T>),
+    Sheet(SheetExclusion<T>),
+    Hilbert(HilbertExclusion<T>),
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(Arc<E>),
+}
+
+impl<T, E: ?Sized> Zone<T, E> {
+    /// Returns the number of distances computed by each call to [`must_be_in`](Exclusion::must_be_in) or
+    /// [`must_be_out`](Exclusion::must_be_out), or `None` for custom zones, whose cost is unknown.
+    pub(crate) fn distances(&self) -> Option<usize> {
+        match self {
+            Zone::Ball(_) => Some(1),
+            Zone::Sheet(_) | Zone::Hilbert(_) => Some(2),
+            Zone::Custom(_) => None,
+        }
+    }
+}
+
+impl<T, E> Exclusion<T> for Zone<T, E>
+where
+    T: Metric,
+    E: Exclusion<T> + ?Sized,
+{
+    fn is_in(&self, point: &T) -> bool {
+        match self {
+            Zone::Ball(ez) => ez.is_in(point),
+            Zone::Sheet(ez) => ez.is_in(point),
+            Zone::Hilbert(ez) => ez.is_in(point),
+            Zone::Custom(ez) => ez.is_in(point),
+        }
+    }
+
+    fn must_be_in(&self, point: &T, threshold: f64) -> bool {
+        match self {
+            Zone::Ball(ez) => ez.must_be_in(point, threshold),
+            Zone::Sheet(ez) => ez.must_be_in(point, threshold),
+            Zone::Hilbert(ez) => ez.must_be_in(point, threshold),
+            Zone::Custom(ez) => ez.must_be_in(point, threshold),
+        }
+    }
+
+    fn must_be_out(&self, point: &T, threshold: f64) -> bool {
+        match self {
+            Zone::Ball(ez) => ez.must_be_out(point, threshold),
+            Zone::Sheet(ez) => ez.must_be_out(point, threshold),
+            Zone::Hilbert(ez) => ez.must_be_out(point, threshold),
+            Zone::Custom(ez) => ez.must_be_out(point, threshold),
+        }
+    }
+}
+
+#[cfg(feature = "par")]
+impl<T> ExclusionSync<T> for Zone<T> where T: Metric + Send + Sync {}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::metric::Euclidean;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Custom exclusion zone containing every point whose coordinate in dimension `dim` is less than `value`.
+    pub(crate) struct Dimension {
+        pub(crate) dim: usize,
+        pub(crate) value: f64,
+    }
+
+    impl Dimension {
+        fn coord<T>(&self, point: &Euclidean<T>) -> f64
+        where
+            for<'a> &'a T: IntoIterator<Item = &'a f64>,
+        {
+            *point.into_iter().nth(self.dim).unwrap()
+        }
+    }
+
+    impl<T> Exclusion<Euclidean<T>> for Dimension
+    where
+        for<'a> &'a T: IntoIterator<Item = &'a f64>,
+        T: Clone,
+    {
+        fn is_in(&self, point: &Euclidean<T>) -> bool {
+            self.coord::<T>(point) < self.value
+        }
+
+        fn must_be_in(&self, point: &Euclidean<T>, threshold: f64) -> bool {
+            self.coord::<T>(point) < self.value - threshold
+        }
+
+        fn must_be_out(&self, point: &Euclidean<T>, threshold: f64) -> bool {
+            self.coord::<T>(point) >= self.value + threshold
+        }
+    }
+
+    /// Checks that `ez` never excludes a point which is within `threshold` of `query`.
+    fn check_exclusion<E>(ez: &E, points: &[Euclidean<Vec<f64>>], threshold: f64)
+    where
+        E: Exclusion<Euclidean<Vec<f64>>>,
+    {
+        for query in points {
+            let must_be_in = ez.must_be_in(query, threshold);
+            let must_be_out = ez.must_be_out(query, threshold);
+
+            for pt in points.iter().filter(|pt| pt.distance(query) <= threshold) {
+                assert!(!must_be_in || ez.is_in(pt));
+                assert!(!must_be_out || !ez.is_in(pt));
+            }
+        }
+    }
+
+    #[test]
+    fn hilbert_exclusion() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let points = (0..500)
+            .map(|_| Euclidean::new((0..8).map(|_| rng.gen::<f64>()).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+
+        let hilbert = HilbertExclusion::new(points[0].clone(), points[1].clone(), 0.1);
+        let sheet = SheetExclusion::new(points[0].clone(), points[1].clone(), 0.1);
+
+        check_exclusion(&hilbert, &points, 0.3);
+        check_exclusion(&sheet, &points, 0.3);
+
+        // The four-point exclusion is at least as tight as the three-point exclusion
+        let excluded = |ez: &dyn Exclusion<Euclidean<Vec<f64>>>| {
+            points
+                .iter()
+                .filter(|q| ez.must_be_in(q, 0.3) || ez.must_be_out(q, 0.3))
+                .count()
+        };
+        assert!(excluded(&hilbert) >= excluded(&sheet));
+    }
+
+    #[test]
+    fn custom_exclusion() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let points = (0..500)
+            .map(|_| Euclidean::new((0..8).map(|_| rng.gen::<f64>()).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+
+        let ez = Arc::new(Dimension { dim: 3, value: 0.5 });
+
+        check_exclusion(&ez, &points, 0.1);
+    }
+}
@@ -0,0 +1,146 @@
+//! k-nearest-neighbour search, shared by every BitPart variant.
+//!
+//! Each variant supplies its own candidate filtering and distance calculation, and [`search`] shrinks the search radius as
+//! closer neighbours are found.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+/// Number of candidates whose distances are calculated before the candidate set is first rebuilt with a smaller radius.
+///
+/// The batch doubles after every rebuild, so that slowly shrinking radii do not rebuild the candidate set for every batch.
+const BATCH: usize = 1024;
+
+/// A point index and its distance from the query, ordered by distance.
+struct Neighbour(usize, f64);
+
+impl PartialEq for Neighbour {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Neighbour {}
+
+impl PartialOrd for Neighbour {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Neighbour {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // `total_cmp` so that a NaN distance from a user metric sorts last instead of panicking
+        self.1.total_cmp(&other.1).then(self.0.cmp(&other.0))
+    }
+}
+
+/// The `k` closest points seen so far, with the farthest on top.
+struct Neighbours {
+    k: usize,
+    heap: BinaryHeap<Neighbour>,
+}
+
+impl Neighbours {
+    fn new(k: usize) -> Self {
+        Self {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+
+    fn push(&mut self, index: usize, distance: f64) {
+        self.heap.push(Neighbour(index, distance));
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+    }
+
+    /// Distance to the `k`-th closest point seen so far, which bounds the distance to the `k`-th nearest neighbour.
+    fn radius(&self) -> f64 {
+        match self.heap.peek() {
+            Some(Neighbour(_, d)) if self.heap.len() == self.k => *d,
+            _ => f64::INFINITY,
+        }
+    }
+}
+
+/// Finds the `k` nearest of `len` points, skipping any which are `removed`.
+///
+/// `candidates` returns the indices of points which could not be excluded from a range search with the given radius, and
+/// `distances` returns the distance from the query to each of the given points.
+///
+/// The search is seeded with the points sharing the query's partition, which are cheap to find and likely to be close. Their
+/// distances bound the search radius, which shrinks as closer candidates are found. Candidates are checked in batches, and if the
+/// radius has shrunk after a batch the candidate set is rebuilt with the smaller radius. The search ends once every candidate for the
+/// current radius has been checked.
+pub(crate) fn search<E>(
+    k: usize,
+    len: usize,
+    removed: impl Fn(usize) -> bool,
+    mut candidates: impl FnMut(f64) -> Result<Vec<usize>, E>,
+    distances: impl Fn(&[usize]) -> Result<Vec<f64>, E>,
+) -> Result<Vec<(usize, f64)>, E> {
+    if k == 0 {
+        return Ok(vec![]);
+    }
+
+    // With a threshold of zero every zone is either "must be in" or "must be out", so the candidates
+    // are exactly the points sharing the query's partition.
+    let mut seeds = candidates(0.0)?;
+    seeds.truncate(k);
+    let cell = seeds.iter().copied().collect::<HashSet<_>>();
+    seeds.extend(
+        (0..len)
+            .filter(|i| !cell.contains(i) && !removed(*i))
+            .take(k - seeds.len()),
+    );
+
+    let mut nn = Neighbours::new(k);
+    for (idx, d) in seeds.iter().zip(distances(&seeds)?) {
+        nn.push(*idx, d);
+    }
+
+    // Fewer than `k` seeds means there are fewer than `k` points left in the dataset
+    if seeds.len() < k {
+        return Ok(sorted(nn));
+    }
+
+    let mut seen = vec![false; len];
+    for idx in seeds {
+        seen[idx] = true;
+    }
+
+    let mut batch = BATCH;
+    'search: loop {
+        let radius = nn.radius();
+        let unseen = candidates(radius)?
+            .into_iter()
+            .filter(|idx| !seen[*idx])
+            .collect::<Vec<_>>();
+
+        for (i, chunk) in unseen.chunks(batch).enumerate() {
+            for (idx, d) in chunk.iter().zip(distances(chunk)?) {
+                nn.push(*idx, d);
+                seen[*idx] = true;
+            }
+
+            // Only rebuild if enough candidates are left for a smaller radius to pay off
+            let remaining = unseen.len().saturating_sub((i + 1) * batch);
+            if nn.radius().total_cmp(&radius).is_lt() && remaining > batch {
+                batch *= 2;
+                continue 'search;
+            }
+        }
+
+        return Ok(sorted(nn));
+    }
+}
+
+fn sorted(nn: Neighbours) -> Vec<(usize, f64)> {
+    nn.heap
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Neighbour(idx, d)| (idx, d))
+        .collect()
+}
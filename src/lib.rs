@@ -33,19 +33,29 @@
 //! # Features
 //! - `rayon`: Enables parallelised BitPart
 //! - `disk`: Enables on-disk BitPart.
-//! - `serde`: Enables (de)serialization through [`serde`](serde)
+//! - `serde`: Enables (de)serialization through [`serde`](serde), including of [`Sequential`] and `Parallel` indexes
 
 #![deny(missing_docs)]
 
 mod builder;
 pub use builder::*;
 
+#[cfg(feature = "serde")]
+mod columns;
+
+mod cull;
+
+mod knn;
+
 pub mod exclusions;
 pub mod metric;
 
 mod sequential;
 pub use sequential::*;
 
+mod stats;
+pub use stats::*;
+
 #[cfg(feature = "par")]
 mod parallel;
 #[cfg(feature = "par")]
@@ -56,6 +66,9 @@ mod on_disk;
 #[cfg(feature = "disk")]
 pub use on_disk::*;
 
+#[cfg(test)]
+mod tests;
+
 /// Trait for BitPart data structures.
 pub trait BitPart<T> {
     /// Corresponding error type for the data structure.
@@ -66,6 +79,31 @@ pub trait BitPart<T> {
     /// Returns a vector of points which fall within the specific radius, along with their distance from the query `point`.
     fn range_search(&self, point: T, threshold: f64) -> Result<Vec<(T, f64)>, Self::Error>;
 
+    /// Perform a range search, given a `point` and a radius `threshold` around it.
+    ///
+    /// Identical to [`range_search`](BitPart::range_search), but returns the index of each point in the dataset instead of a clone of the point.
+    fn range_search_indices(
+        &self,
+        point: T,
+        threshold: f64,
+    ) -> Result<Vec<(usize, f64)>, Self::Error>;
+
+    /// Perform a range search, given a `point` and a radius `threshold` around it, recording [`QueryStats`] for each phase of the search.
+    ///
+    /// Returns the same results as [`range_search_indices`](BitPart::range_search_indices), alongside the statistics.
+    fn range_search_stats(&self, point: T, threshold: f64) -> Result<RangeStats, Self::Error>;
+
+    /// Perform a k-nearest-neighbour search, given a `point` and the number of neighbours `k` to find.
+    ///
+    /// Returns a vector of the `k` points closest to the query `point` (or the entire dataset, if it contains fewer than `k` points),
+    /// along with their distance from `point`, sorted by ascending distance. Ties are broken arbitrarily.
+    fn knn_search(&self, point: T, k: usize) -> Result<Vec<(T, f64)>, Self::Error>;
+
+    /// Perform a k-nearest-neighbour search, given a `point` and the number of neighbours `k` to find.
+    ///
+    /// Identical to [`knn_search`](BitPart::knn_search), but returns the index of each point in the dataset instead of a clone of the point.
+    fn knn_search_indices(&self, point: T, k: usize) -> Result<Vec<(usize, f64)>, Self::Error>;
+
     /// Returns the size of the dataset.
     fn len(&self) -> usize;
 
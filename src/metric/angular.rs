@@ -0,0 +1,290 @@
+use std::{ops::Deref, sync::OnceLock};
+
+use super::Metric;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Wrapper struct to apply angular distance to an object set.
+///
+/// The angular distance between two points is the angle between them as vectors, in radians: the arccos of their normalised dot
+/// product. Unlike cosine distance, it satisfies the triangle inequality. Points pointing in the same direction are at distance
+/// zero whatever their magnitude, and the zero vector is at a right angle to every other point.
+///
+/// Norms are computed on every distance calculation; [`NormalizedAngular`] computes each once and caches it.
+/// # Example
+/// ```
+/// # use bitpart::metric::{Angular, Metric};
+/// #
+/// let point1: Angular<[f64; 2]> = Angular::new([1.0, 0.0]);
+/// let point2: Angular<[f64; 2]> = Angular::new([0.0, 2.0]);
+///
+/// assert_eq!(point1.distance(&point2), std::f64::consts::FRAC_PI_2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Angular<T>(T);
+
+impl<T> Angular<T> {
+    /// Creates a new `Angular`.
+    pub fn new(t: T) -> Self {
+        Self(t)
+    }
+
+    /// Consumes the `Angular`, returning the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Angular<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> IntoIterator for Angular<T>
+where
+    T: IntoIterator,
+{
+    type Item = <T as IntoIterator>::Item;
+    type IntoIter = <T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Angular<T>
+where
+    &'a T: IntoIterator,
+{
+    type Item = <&'a T as IntoIterator>::Item;
+    type IntoIter = <&'a T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<T> Metric for Angular<T>
+where
+    for<'a> &'a T: IntoIterator<Item = &'a f64>,
+    T: Clone,
+{
+    fn distance(&self, rhs: &Angular<T>) -> f64 {
+        angle(&self.0, &rhs.0, scale(&self.0), scale(&rhs.0))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T> Serialize for Angular<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> Deserialize<'de> for Angular<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Angular::new(T::deserialize(deserializer)?))
+    }
+}
+
+/// Wrapper struct to apply angular distance to an object set, with each point's norm computed once and cached.
+///
+/// Distances are identical to those of [`Angular`], but are faster to compute at the cost of storing a norm with every point.
+/// A norm is computed by the first distance calculation involving its point. Norms are not serialized, and are recomputed after
+/// deserialization.
+/// # Example
+/// ```
+/// # use bitpart::metric::{NormalizedAngular, Metric};
+/// #
+/// let point1: NormalizedAngular<[f64; 2]> = NormalizedAngular::new([1.0, 0.0]);
+/// let point2: NormalizedAngular<[f64; 2]> = NormalizedAngular::new([0.0, 2.0]);
+///
+/// assert_eq!(point1.distance(&point2), std::f64::consts::FRAC_PI_2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct NormalizedAngular<T> {
+    point: T,
+    scale: OnceLock<f64>,
+}
+
+impl<T> NormalizedAngular<T> {
+    /// Creates a new `NormalizedAngular`.
+    pub fn new(t: T) -> Self {
+        Self {
+            point: t,
+            scale: OnceLock::new(),
+        }
+    }
+
+    /// Consumes the `NormalizedAngular`, returning the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.point
+    }
+}
+
+impl<T> Deref for NormalizedAngular<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.point
+    }
+}
+
+impl<T> IntoIterator for NormalizedAngular<T>
+where
+    T: IntoIterator,
+{
+    type Item = <T as IntoIterator>::Item;
+    type IntoIter = <T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.point.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a NormalizedAngular<T>
+where
+    &'a T: IntoIterator,
+{
+    type Item = <&'a T as IntoIterator>::Item;
+    type IntoIter = <&'a T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.point.into_iter()
+    }
+}
+
+impl<T> Metric for NormalizedAngular<T>
+where
+    for<'a> &'a T: IntoIterator<Item = &'a f64>,
+    T: Clone,
+{
+    fn distance(&self, rhs: &NormalizedAngular<T>) -> f64 {
+        angle(
+            &self.point,
+            &rhs.point,
+            *self.scale.get_or_init(|| scale(&self.point)),
+            *rhs.scale.get_or_init(|| scale(&rhs.point)),
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T> Serialize for NormalizedAngular<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.point.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> Deserialize<'de> for NormalizedAngular<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(NormalizedAngular::new(T::deserialize(deserializer)?))
+    }
+}
+
+/// Returns the factor which scales a vector to a unit vector, or zero for the zero vector.
+fn scale<'a>(t: impl IntoIterator<Item = &'a f64>) -> f64 {
+    let norm = t.into_iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm == 0.0 {
+        0.0
+    } else {
+        1.0 / norm
+    }
+}
+
+/// Returns the angle between `lhs` and `rhs`, scaled to unit vectors by `lhs_scale` and `rhs_scale`.
+fn angle<'a>(
+    lhs: impl IntoIterator<Item = &'a f64>,
+    rhs: impl IntoIterator<Item = &'a f64>,
+    lhs_scale: f64,
+    rhs_scale: f64,
+) -> f64 {
+    // The angle between unit vectors u and v is 2 * atan2(|u - v|, |u + v|), which equals acos(u · v) but is exactly zero
+    // for identical vectors, and stays accurate for small angles where acos loses precision.
+    let (diff, sum) = lhs
+        .into_iter()
+        .zip(rhs)
+        .map(|(x, y)| (x * lhs_scale, y * rhs_scale))
+        .fold((0.0, 0.0), |(diff, sum), (x, y)| {
+            (diff + (x - y).powi(2), sum + (x + y).powi(2))
+        });
+
+    2.0 * diff.sqrt().atan2(sum.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::tests::{axioms, nasa, range_search};
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    #[test]
+    fn angular_2d() {
+        let point = Angular::new([1.0, 0.0]);
+
+        assert_eq!(point.distance(&Angular::new([3.0, 0.0])), 0.0);
+        assert!((point.distance(&Angular::new([1.0, 1.0])) - FRAC_PI_4).abs() < 1e-12);
+        assert_eq!(point.distance(&Angular::new([-2.0, 0.0])), PI);
+        assert_eq!(point.distance(&Angular::new([0.0, 0.0])), FRAC_PI_2);
+        assert_eq!(
+            Angular::new([0.0, 0.0]).distance(&Angular::new([0.0, 0.0])),
+            0.0
+        );
+    }
+
+    #[test]
+    fn normalized_matches() {
+        let points = [[0.5, -1.0, 3.0], [2.0, 1.0, -0.25], [0.0, 0.0, 0.0]];
+
+        for x in points {
+            for y in points {
+                assert_eq!(
+                    Angular::new(x).distance(&Angular::new(y)),
+                    NormalizedAngular::new(x).distance(&NormalizedAngular::new(y))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn angular_axioms() {
+        axioms(Angular::new);
+        axioms(NormalizedAngular::new);
+    }
+
+    #[test]
+    fn sisap_nasa_angular() {
+        let points = nasa().into_iter().map(NormalizedAngular::new).collect();
+        range_search(points, 317, 0.9);
+    }
+}
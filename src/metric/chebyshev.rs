@@ -0,0 +1,123 @@
+use std::ops::Deref;
+
+use super::Metric;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Wrapper struct to apply Chebyshev (L∞) distance to an object set.
+/// # Example
+/// ```
+/// # use bitpart::metric::{Chebyshev, Metric};
+/// #
+/// let point1: Chebyshev<[f64; 2]> = Chebyshev::new([0.0, 0.0]);
+/// let point2: Chebyshev<[f64; 2]> = Chebyshev::new([1.0, 2.0]);
+///
+/// assert_eq!(point1.distance(&point2), 2.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Chebyshev<T>(T);
+
+impl<T> Chebyshev<T> {
+    /// Creates a new `Chebyshev`.
+    pub fn new(t: T) -> Self {
+        Self(t)
+    }
+
+    /// Consumes the `Chebyshev`, returning the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Chebyshev<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> IntoIterator for Chebyshev<T>
+where
+    T: IntoIterator,
+{
+    type Item = <T as IntoIterator>::Item;
+    type IntoIter = <T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Chebyshev<T>
+where
+    &'a T: IntoIterator,
+{
+    type Item = <&'a T as IntoIterator>::Item;
+    type IntoIter = <&'a T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<T> Metric for Chebyshev<T>
+where
+    for<'a> &'a T: IntoIterator<Item = &'a f64>,
+    T: Clone,
+{
+    fn distance(&self, rhs: &Chebyshev<T>) -> f64 {
+        // Chebyshev distance is the largest |point1 - point2| of any dimension.
+        self.0
+            .into_iter()
+            .zip(&rhs.0)
+            .map(|(x, y)| (x - y).abs())
+            .fold(0.0, f64::max)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T> Serialize for Chebyshev<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> Deserialize<'de> for Chebyshev<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Chebyshev::new(T::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::tests::axioms;
+
+    #[test]
+    fn chebyshev_2d() {
+        let point1: Chebyshev<[f64; 2]> = Chebyshev::new([0.0, 0.0]);
+        let point2: Chebyshev<[f64; 2]> = Chebyshev::new([1.0, -2.0]);
+
+        assert_eq!(point1.distance(&point2), 2.0);
+    }
+
+    #[test]
+    fn chebyshev_axioms() {
+        axioms(Chebyshev::new);
+    }
+}
@@ -0,0 +1,269 @@
+#[cfg(feature = "serde")]
+use std::cell::RefCell;
+use std::{
+    ops::Deref,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use super::{FourPoint, Metric, SuperMetric};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Thread-safe count of distance calculations, shared by every [`Counting`] point created from it.
+///
+/// Clones share the same count.
+#[derive(Debug, Clone, Default)]
+pub struct Counter(Arc<AtomicUsize>);
+
+impl Counter {
+    /// Creates a new `Counter`, starting at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of distance calculations counted since creation or the last [`reset`](Counter::reset).
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Resets the count to zero, returning the previous count.
+    pub fn reset(&self) -> usize {
+        self.0.swap(0, Ordering::Relaxed)
+    }
+
+    /// Runs `f`, giving every [`Counting`] point deserialized by this thread while it runs this counter, instead of a new one.
+    ///
+    /// An index read back with `counter.attach(|| Disk::open(path))` counts with `counter`, rather than with a new counter for
+    /// each point. The points of a memory mapped dataset are only deserialized when a query reads them, so they are not given
+    /// this counter, but a query still counts every calculation with the counter of the query point.
+    #[cfg(feature = "serde")]
+    pub fn attach<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct Detach(Option<Counter>);
+
+        impl Drop for Detach {
+            fn drop(&mut self) {
+                ATTACHED.with(|attached| *attached.borrow_mut() = self.0.take());
+            }
+        }
+
+        let _detach = Detach(ATTACHED.with(|attached| attached.replace(Some(self.clone()))));
+        f()
+    }
+
+    fn increment(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "serde")]
+thread_local! {
+    /// The counter given to deserialized points by [`Counter::attach`].
+    static ATTACHED: RefCell<Option<Counter>> = const { RefCell::new(None) };
+}
+
+/// Wrapper struct to count the distance calculations of another metric.
+///
+/// Every call to [`distance`](Metric::distance) increments the [`Counter`] of the left-hand point, so points sharing a counter
+/// count calculations made from any thread, such as during a [`Parallel`](crate::Parallel) query. Queries calculate every
+/// distance from the query point, so the counter of the query is the one that counts a search.
+/// # Example
+/// ```
+/// # use bitpart::metric::{Counter, Counting, Euclidean, Metric};
+/// #
+/// let counter = Counter::new();
+/// let point1 = Counting::new(Euclidean::new([0.0, 0.0]), &counter);
+/// let point2 = Counting::new(Euclidean::new([1.0, 1.0]), &counter);
+///
+/// assert_eq!(point1.distance(&point2), 2.0_f64.sqrt());
+/// assert_eq!(counter.get(), 1);
+/// ```
+///
+/// With the `serde` feature, only the wrapped metric is serialized. A deserialized point counts with the counter
+/// [attached](Counter::attach) to the thread, or a new [`Counter`] of its own if there is none.
+#[derive(Debug, Clone)]
+pub struct Counting<M> {
+    metric: M,
+    counter: Counter,
+}
+
+impl<M> Counting<M> {
+    /// Creates a new `Counting`, which counts its distance calculations with `counter`.
+    pub fn new(metric: M, counter: &Counter) -> Self {
+        Self {
+            metric,
+            counter: counter.clone(),
+        }
+    }
+
+    /// Returns the counter of this point.
+    pub fn counter(&self) -> &Counter {
+        &self.counter
+    }
+
+    /// Consumes the `Counting`, returning the wrapped value.
+    pub fn into_inner(self) -> M {
+        self.metric
+    }
+}
+
+impl<M> Deref for Counting<M> {
+    type Target = M;
+
+    fn deref(&self) -> &Self::Target {
+        &self.metric
+    }
+}
+
+impl<M> Metric for Counting<M>
+where
+    M: Metric,
+{
+    // `Counting<M>` is a supermetric exactly when `M` is
+    const SUPERMETRIC: Option<FourPoint<Self>> = match M::SUPERMETRIC {
+        Some(proof) => Some(proof.cast()),
+        None => None,
+    };
+
+    fn distance(&self, rhs: &Self) -> f64 {
+        self.counter.increment();
+        self.metric.distance(&rhs.metric)
+    }
+}
+
+impl<M> SuperMetric for Counting<M> where M: SuperMetric {}
+
+#[cfg(feature = "serde")]
+impl<M> Serialize for Counting<M>
+where
+    M: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.metric.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, M> Deserialize<'de> for Counting<M>
+where
+    M: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let counter = ATTACHED.with(|attached| attached.borrow().clone());
+
+        Ok(Counting {
+            metric: M::deserialize(deserializer)?,
+            counter: counter.unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        metric::{tests::nasa, Euclidean},
+        BitPart, Builder,
+    };
+
+    fn counted_nasa(counter: &Counter) -> Vec<Counting<Euclidean<Vec<f64>>>> {
+        nasa()
+            .into_iter()
+            .map(|pt| Counting::new(Euclidean::new(pt), counter))
+            .collect()
+    }
+
+    #[test]
+    fn reset() {
+        let counter = Counter::new();
+        let point = Counting::new(Euclidean::new([0.0, 0.0]), &counter);
+
+        for _ in 0..3 {
+            point.distance(&point);
+        }
+        assert_eq!(counter.reset(), 3);
+        assert_eq!(counter.get(), 0);
+    }
+
+    #[test]
+    fn sisap_nasa_count() {
+        let counter = Counter::new();
+        let nasa = counted_nasa(&counter);
+        let queries = Counter::new();
+        let query = Counting::new(nasa[317].clone().into_inner(), &queries);
+        let threshold = 1.0;
+
+        let bitpart = Builder::new(nasa.clone(), 40).build();
+        counter.reset();
+
+        // Searching calculates fewer distances than a linear search, which calculates one per point, all counted by the query
+        let res = bitpart.range_search(query.clone(), threshold).unwrap();
+        let searched = queries.reset();
+        assert!(searched > 0 && searched < nasa.len());
+        assert_eq!(counter.reset(), 0);
+
+        let linear = nasa
+            .iter()
+            .filter(|pt| pt.distance(&query) <= threshold)
+            .count();
+        assert_eq!(res.len(), linear);
+        assert_eq!(counter.get(), nasa.len());
+    }
+
+    #[cfg(feature = "par")]
+    #[test]
+    fn sisap_nasa_count_par() {
+        let counter = Counter::new();
+        let nasa = counted_nasa(&counter);
+        let query = nasa[317].clone();
+        let threshold = 1.0;
+
+        let sequential = Builder::new(nasa.clone(), 40).build();
+        let parallel = Builder::new(nasa.clone(), 40).build_parallel(Some(512));
+        counter.reset();
+
+        // Parallel queries count the same calculations as sequential queries
+        sequential.range_search(query.clone(), threshold).unwrap();
+        let searched = counter.reset();
+        parallel.range_search(query.clone(), threshold).unwrap();
+        assert_eq!(counter.get(), searched);
+    }
+
+    #[cfg(feature = "disk")]
+    #[test]
+    fn sisap_nasa_count_disk() {
+        let dir = crate::tests::TempPath::new("sisap_nasa_count_disk");
+        let counter = Counter::new();
+        let nasa = counted_nasa(&counter);
+        let query = nasa[317].clone();
+        let threshold = 1.0;
+
+        let sequential = Builder::new(nasa.clone(), 40).build();
+        let disk = Builder::new(nasa, 40)
+            .build_on_disk(&dir, Some(512))
+            .unwrap();
+        drop(disk);
+
+        // Reopened points count with the attached counter, and a reopened index counts the same distances as the original
+        let disk = counter.attach(|| crate::Disk::open(&dir)).unwrap();
+        counter.reset();
+        let res = sequential.range_search(query.clone(), threshold).unwrap();
+        let searched = counter.reset();
+        assert_eq!(
+            disk.range_search(query.clone(), threshold).unwrap().len(),
+            res.len()
+        );
+        assert_eq!(counter.reset(), searched);
+
+        disk.get(0).unwrap().unwrap().distance(&query);
+        assert_eq!(counter.get(), 1);
+    }
+}
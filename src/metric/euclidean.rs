@@ -1,6 +1,6 @@
 use std::ops::{Deref, Sub};
 
-use super::Metric;
+use super::{FourPoint, Metric, SuperMetric};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -67,17 +67,26 @@ where
     for<'a> &'a T: IntoIterator<Item = &'a f64>,
     T: Clone,
 {
+    const SUPERMETRIC: Option<FourPoint<Self>> = Some(FourPoint::new());
+
     fn distance(&self, rhs: &Euclidean<T>) -> f64 {
         // Euclidean distance is the sqrt of the sum of (point1 - point2)^2 for each dimension.
         self.0
             .into_iter()
-            .zip(rhs.0.into_iter())
+            .zip(&rhs.0)
             .map(|(x, y)| (x.sub(*y)).powi(2))
             .sum::<f64>()
             .sqrt()
     }
 }
 
+impl<T> SuperMetric for Euclidean<T>
+where
+    for<'a> &'a T: IntoIterator<Item = &'a f64>,
+    T: Clone,
+{
+}
+
 #[cfg(feature = "serde")]
 impl<T> Serialize for Euclidean<T>
 where
@@ -107,6 +116,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::metric::tests::axioms;
 
     #[test]
     fn euclidean_2d() {
@@ -115,4 +125,9 @@ mod tests {
 
         assert_eq!(point1.distance(&point2), 2.0_f64.sqrt());
     }
+
+    #[test]
+    fn euclidean_axioms() {
+        axioms(Euclidean::new);
+    }
 }
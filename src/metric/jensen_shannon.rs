@@ -0,0 +1,177 @@
+use std::ops::Deref;
+
+use super::{FourPoint, Metric, SuperMetric};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Wrapper struct to apply Jensen–Shannon distance to a set of probability vectors, such as histograms.
+///
+/// The Jensen–Shannon distance is the square root of the Jensen–Shannon [divergence](JensenShannon::divergence), which is not
+/// itself a metric. Taking the square root makes it both a metric and a [`SuperMetric`], so tighter four-point sheet exclusions
+/// can be used. Points should be non-negative and sum to one, giving distances between zero and one.
+/// # Example
+/// ```
+/// # use bitpart::metric::{JensenShannon, Metric};
+/// #
+/// let point1: JensenShannon<[f64; 2]> = JensenShannon::new([1.0, 0.0]);
+/// let point2: JensenShannon<[f64; 2]> = JensenShannon::new([0.0, 1.0]);
+///
+/// assert_eq!(point1.distance(&point2), 1.0);
+/// assert_eq!(point1.divergence(&point2), 1.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct JensenShannon<T>(T);
+
+impl<T> JensenShannon<T> {
+    /// Creates a new `JensenShannon`.
+    pub fn new(t: T) -> Self {
+        Self(t)
+    }
+
+    /// Consumes the `JensenShannon`, returning the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for JensenShannon<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> IntoIterator for JensenShannon<T>
+where
+    T: IntoIterator,
+{
+    type Item = <T as IntoIterator>::Item;
+    type IntoIter = <T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a JensenShannon<T>
+where
+    &'a T: IntoIterator,
+{
+    type Item = <&'a T as IntoIterator>::Item;
+    type IntoIter = <&'a T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<T> JensenShannon<T>
+where
+    for<'a> &'a T: IntoIterator<Item = &'a f64>,
+{
+    /// Returns the Jensen–Shannon divergence between two points, in bits.
+    ///
+    /// This is the mean of the Kullback–Leibler divergences of each point from their mean.
+    pub fn divergence(&self, rhs: &JensenShannon<T>) -> f64 {
+        let entropy = |x: f64, mean: f64| match x {
+            0.0 => 0.0,
+            x => x * (x / mean).log2(),
+        };
+
+        let divergence = self
+            .0
+            .into_iter()
+            .zip(&rhs.0)
+            .map(|(x, y)| {
+                let mean = (x + y) / 2.0;
+                entropy(*x, mean) + entropy(*y, mean)
+            })
+            .sum::<f64>()
+            / 2.0;
+
+        // Rounding can leave the divergence of near-identical points just below zero
+        divergence.max(0.0)
+    }
+}
+
+impl<T> Metric for JensenShannon<T>
+where
+    for<'a> &'a T: IntoIterator<Item = &'a f64>,
+    T: Clone,
+{
+    const SUPERMETRIC: Option<FourPoint<Self>> = Some(FourPoint::new());
+
+    fn distance(&self, rhs: &JensenShannon<T>) -> f64 {
+        self.divergence(rhs).sqrt()
+    }
+}
+
+impl<T> SuperMetric for JensenShannon<T>
+where
+    for<'a> &'a T: IntoIterator<Item = &'a f64>,
+    T: Clone,
+{
+}
+
+#[cfg(feature = "serde")]
+impl<T> Serialize for JensenShannon<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> Deserialize<'de> for JensenShannon<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(JensenShannon::new(T::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::tests::{colors, histogram_axioms, range_search};
+
+    #[test]
+    fn jensen_shannon_2d() {
+        let point1: JensenShannon<[f64; 2]> = JensenShannon::new([0.5, 0.5]);
+        let point2: JensenShannon<[f64; 2]> = JensenShannon::new([1.0, 0.0]);
+
+        // The mean of the two points is [0.75, 0.25]
+        let expected =
+            (1.0 * (1.0_f64 / 0.75).log2() + 0.5 * (0.5_f64 / 0.75).log2() + 0.5 * 2.0_f64.log2())
+                / 2.0;
+        assert!((point1.divergence(&point2) - expected).abs() < 1e-12);
+        assert_eq!(point1.distance(&point2), point1.divergence(&point2).sqrt());
+        assert_eq!(point1.distance(&point1), 0.0);
+    }
+
+    #[test]
+    fn jensen_shannon_axioms() {
+        histogram_axioms(JensenShannon::new);
+    }
+
+    #[test]
+    fn sisap_colors_jensen_shannon() {
+        let points = colors()
+            .into_iter()
+            .step_by(10)
+            .map(JensenShannon::new)
+            .collect();
+        range_search(points, 7044, 0.62);
+    }
+}
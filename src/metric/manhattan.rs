@@ -0,0 +1,123 @@
+use std::ops::Deref;
+
+use super::Metric;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Wrapper struct to apply Manhattan (L1) distance to an object set.
+/// # Example
+/// ```
+/// # use bitpart::metric::{Manhattan, Metric};
+/// #
+/// let point1: Manhattan<[f64; 2]> = Manhattan::new([0.0, 0.0]);
+/// let point2: Manhattan<[f64; 2]> = Manhattan::new([1.0, 1.0]);
+///
+/// assert_eq!(point1.distance(&point2), 2.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Manhattan<T>(T);
+
+impl<T> Manhattan<T> {
+    /// Creates a new `Manhattan`.
+    pub fn new(t: T) -> Self {
+        Self(t)
+    }
+
+    /// Consumes the `Manhattan`, returning the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Manhattan<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> IntoIterator for Manhattan<T>
+where
+    T: IntoIterator,
+{
+    type Item = <T as IntoIterator>::Item;
+    type IntoIter = <T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Manhattan<T>
+where
+    &'a T: IntoIterator,
+{
+    type Item = <&'a T as IntoIterator>::Item;
+    type IntoIter = <&'a T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<T> Metric for Manhattan<T>
+where
+    for<'a> &'a T: IntoIterator<Item = &'a f64>,
+    T: Clone,
+{
+    fn distance(&self, rhs: &Manhattan<T>) -> f64 {
+        // Manhattan distance is the sum of |point1 - point2| for each dimension.
+        self.0
+            .into_iter()
+            .zip(&rhs.0)
+            .map(|(x, y)| (x - y).abs())
+            .sum()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T> Serialize for Manhattan<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> Deserialize<'de> for Manhattan<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Manhattan::new(T::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::tests::axioms;
+
+    #[test]
+    fn manhattan_2d() {
+        let point1: Manhattan<[f64; 2]> = Manhattan::new([0.0, 0.0]);
+        let point2: Manhattan<[f64; 2]> = Manhattan::new([1.0, -2.0]);
+
+        assert_eq!(point1.distance(&point2), 3.0);
+    }
+
+    #[test]
+    fn manhattan_axioms() {
+        axioms(Manhattan::new);
+    }
+}
@@ -0,0 +1,164 @@
+use std::ops::Deref;
+
+use super::{FourPoint, Metric, SuperMetric};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Wrapper struct to apply Minkowski (Lp) distance of order `P` to an object set.
+///
+/// `Minkowski<T, 1>` is equivalent to [`Manhattan`](super::Manhattan) and `Minkowski<T, 2>` to [`Euclidean`](super::Euclidean),
+/// which are faster to compute. `P` must be at least 1, as the triangle inequality does not hold otherwise, and at most
+/// [`i32::MAX`]; both are checked at compile time. Only `Minkowski<T, 2>` is a [`SuperMetric`].
+/// # Example
+/// ```
+/// # use bitpart::metric::{Minkowski, Metric};
+/// #
+/// let point1: Minkowski<[f64; 2], 3> = Minkowski::new([0.0, 0.0]);
+/// let point2: Minkowski<[f64; 2], 3> = Minkowski::new([1.0, 1.0]);
+///
+/// assert_eq!(point1.distance(&point2), 2.0_f64.cbrt());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Minkowski<T, const P: u32>(T);
+
+impl<T, const P: u32> Minkowski<T, P> {
+    /// Evaluated by [`new`](Minkowski::new), so that an invalid `P` fails to compile.
+    const VALID: () = {
+        assert!(P >= 1, "Minkowski distance is only a metric for P >= 1");
+        assert!(
+            P <= i32::MAX as u32,
+            "Minkowski distance requires P <= i32::MAX"
+        );
+    };
+
+    /// Creates a new `Minkowski`.
+    pub fn new(t: T) -> Self {
+        let () = Self::VALID;
+        Self(t)
+    }
+
+    /// Consumes the `Minkowski`, returning the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T, const P: u32> Deref for Minkowski<T, P> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T, const P: u32> IntoIterator for Minkowski<T, P>
+where
+    T: IntoIterator,
+{
+    type Item = <T as IntoIterator>::Item;
+    type IntoIter = <T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T, const P: u32> IntoIterator for &'a Minkowski<T, P>
+where
+    &'a T: IntoIterator,
+{
+    type Item = <&'a T as IntoIterator>::Item;
+    type IntoIter = <&'a T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<T, const P: u32> Metric for Minkowski<T, P>
+where
+    for<'a> &'a T: IntoIterator<Item = &'a f64>,
+    T: Clone,
+{
+    const SUPERMETRIC: Option<FourPoint<Self>> = if P == 2 {
+        Some(FourPoint::<Minkowski<T, 2>>::new().cast())
+    } else {
+        None
+    };
+
+    fn distance(&self, rhs: &Minkowski<T, P>) -> f64 {
+        // Minkowski distance is the Pth root of the sum of |point1 - point2|^P for each dimension.
+        self.0
+            .into_iter()
+            .zip(&rhs.0)
+            .map(|(x, y)| (x - y).abs().powi(P as i32))
+            .sum::<f64>()
+            .powf(1.0 / P as f64)
+    }
+}
+
+impl<T> SuperMetric for Minkowski<T, 2>
+where
+    for<'a> &'a T: IntoIterator<Item = &'a f64>,
+    T: Clone,
+{
+}
+
+#[cfg(feature = "serde")]
+impl<T, const P: u32> Serialize for Minkowski<T, P>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T, const P: u32> Deserialize<'de> for Minkowski<T, P>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Minkowski::new(T::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::{tests::axioms, Euclidean, Manhattan};
+
+    #[test]
+    fn minkowski_2d() {
+        let point1: Minkowski<[f64; 2], 3> = Minkowski::new([0.0, 0.0]);
+        let point2: Minkowski<[f64; 2], 3> = Minkowski::new([1.0, -2.0]);
+
+        assert!((point1.distance(&point2) - 9.0_f64.cbrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn minkowski_orders() {
+        let point1 = [0.5, -1.0, 3.0];
+        let point2 = [2.0, 1.0, -0.25];
+
+        let l1 = Minkowski::<_, 1>::new(point1).distance(&Minkowski::new(point2));
+        let l2 = Minkowski::<_, 2>::new(point1).distance(&Minkowski::new(point2));
+        assert!((l1 - Manhattan::new(point1).distance(&Manhattan::new(point2))).abs() < 1e-12);
+        assert!((l2 - Euclidean::new(point1).distance(&Euclidean::new(point2))).abs() < 1e-12);
+        assert!(Minkowski::<[f64; 3], 2>::SUPERMETRIC.is_some());
+        assert!(Minkowski::<[f64; 3], 3>::SUPERMETRIC.is_none());
+    }
+
+    #[test]
+    fn minkowski_axioms() {
+        axioms(Minkowski::<_, 3>::new);
+    }
+}
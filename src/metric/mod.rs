@@ -1,10 +1,40 @@
 //! Metric space definitions
 
+use std::marker::PhantomData;
+
+mod counting;
+pub use counting::*;
+
+mod angular;
+pub use angular::*;
+
+mod chebyshev;
+pub use chebyshev::*;
+
 mod euclidean;
 pub use euclidean::*;
 
+mod jensen_shannon;
+pub use jensen_shannon::*;
+
+mod manhattan;
+pub use manhattan::*;
+
+mod minkowski;
+pub use minkowski::*;
+
+mod triangular;
+pub use triangular::*;
+
 /// Trait for types in metric space.
 pub trait Metric: Clone {
+    /// Proof that the metric satisfies the four-point property, if it does.
+    ///
+    /// A [`FourPoint`] can only be constructed for types implementing [`SuperMetric`], so only they can set this to
+    /// `Some(FourPoint::new())`. It is used by the [`Builder`](crate::Builder) to select [`four_point`](crate::Builder::four_point)
+    /// sheet exclusions by default.
+    const SUPERMETRIC: Option<FourPoint<Self>> = None;
+
     /// Distance between two points.
     /// # Axioms
     /// For a distance function to be valid, the following axioms must be met:
@@ -19,3 +49,127 @@ pub trait Metric: Clone {
     /// **It is the responsibility of the implementer to ensure that the axiom are met.**
     fn distance(&self, rhs: &Self) -> f64;
 }
+
+/// Marker trait for types in supermetric space.
+///
+/// A supermetric space is a metric space which also has the four-point property: any four points can be isometrically embedded in
+/// three-dimensional Euclidean space. This allows tighter sheet exclusion zones to be used; see [`four_point`](crate::Builder::four_point).
+///
+/// Types implementing this trait should also set [`Metric::SUPERMETRIC`] to `Some(FourPoint::new())`, or four-point sheet exclusions
+/// are not used by default.
+///
+/// **It is the responsibility of the implementer to ensure that the four-point property holds.**
+pub trait SuperMetric: Metric {}
+
+/// Proof that `T` is a [`SuperMetric`], used to set [`Metric::SUPERMETRIC`].
+pub struct FourPoint<T>(PhantomData<fn() -> T>);
+
+impl<T> FourPoint<T>
+where
+    T: SuperMetric,
+{
+    /// Returns the proof that `T` is a supermetric.
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Default for FourPoint<T>
+where
+    T: SuperMetric,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FourPoint<T> {
+    /// Converts a proof for `T` into a proof for `U`, which the caller must ensure is a supermetric whenever `T` is.
+    ///
+    /// Used where the bounds of a [`SuperMetric`] impl can not be named in a `const`, such as metrics wrapping another metric.
+    pub(crate) const fn cast<U>(self) -> FourPoint<U> {
+        FourPoint(PhantomData)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{BitPart, Builder};
+    use sisap_data::{
+        colors::{parse_colors, Colors},
+        nasa::parse_nasa,
+    };
+
+    const NASA: &str = include_str!("../../sisap-data/src/nasa.ascii");
+    const COLORS: &str = include_str!("../../sisap-data/src/colors.ascii");
+
+    /// Returns the points of the SISAP `nasa` dataset.
+    pub(crate) fn nasa() -> Vec<Vec<f64>> {
+        parse_nasa(NASA)
+            .unwrap()
+            .into_iter()
+            .map(|pt| pt.to_vec())
+            .collect()
+    }
+
+    /// Returns the points of the SISAP `colors` dataset, which are probability vectors.
+    pub(crate) fn colors() -> Vec<Colors> {
+        parse_colors(COLORS).unwrap()
+    }
+
+    /// Checks the metric axioms hold between every pair and triple of a sample of the SISAP datasets, wrapped with `wrap`.
+    pub(crate) fn axioms<M: Metric>(wrap: impl Fn(Vec<f64>) -> M) {
+        check_axioms(nasa().into_iter().step_by(400).collect(), &wrap);
+        histogram_axioms(wrap);
+    }
+
+    /// Checks the metric axioms hold on a sample of the SISAP `colors` dataset.
+    pub(crate) fn histogram_axioms<M: Metric>(wrap: impl Fn(Vec<f64>) -> M) {
+        check_axioms(
+            colors()
+                .into_iter()
+                .step_by(1000)
+                .map(|pt| pt.to_vec())
+                .collect(),
+            &wrap,
+        );
+    }
+
+    /// Checks a range search around the point at `query` finds more than the query itself, and the same points as a linear search.
+    pub(crate) fn range_search<M>(points: Vec<M>, query: usize, threshold: f64)
+    where
+        for<'a> M: Metric + 'a,
+    {
+        let query = points[query].clone();
+        let brute_force = points
+            .iter()
+            .filter(|pt| pt.distance(&query) <= threshold)
+            .count();
+
+        let bitpart = Builder::new(points, 40).build();
+        let res = bitpart.range_search(query, threshold).unwrap();
+        assert!(res.len() > 1);
+        assert_eq!(res.len(), brute_force);
+    }
+
+    fn check_axioms<M: Metric>(dataset: Vec<Vec<f64>>, wrap: impl Fn(Vec<f64>) -> M) {
+        let points = dataset.iter().cloned().map(wrap).collect::<Vec<_>>();
+        let distances = points
+            .iter()
+            .map(|x| points.iter().map(|y| x.distance(y)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        for (x, row) in distances.iter().enumerate() {
+            for (y, d) in row.iter().enumerate() {
+                assert_eq!(*d == 0.0, dataset[x] == dataset[y]);
+                assert!(*d >= 0.0);
+                assert_eq!(*d, distances[y][x]);
+
+                for (xz, yz) in row.iter().zip(&distances[y]) {
+                    assert!(*xz <= d + yz + 1e-9);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::ops::Deref;
+
+use super::{FourPoint, Metric, SuperMetric};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Wrapper struct to apply triangular distance to a set of probability vectors, such as histograms.
+///
+/// The triangular distance is the square root of the triangular [discrimination](Triangular::discrimination), which is not
+/// itself a metric. Taking the square root makes it both a metric and a [`SuperMetric`], so tighter four-point sheet exclusions
+/// can be used. Points should be non-negative and sum to one, giving distances between zero and the square root of two.
+/// # Example
+/// ```
+/// # use bitpart::metric::{Triangular, Metric};
+/// #
+/// let point1: Triangular<[f64; 2]> = Triangular::new([1.0, 0.0]);
+/// let point2: Triangular<[f64; 2]> = Triangular::new([0.0, 1.0]);
+///
+/// assert_eq!(point1.distance(&point2), 2.0_f64.sqrt());
+/// assert_eq!(point1.discrimination(&point2), 2.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Triangular<T>(T);
+
+impl<T> Triangular<T> {
+    /// Creates a new `Triangular`.
+    pub fn new(t: T) -> Self {
+        Self(t)
+    }
+
+    /// Consumes the `Triangular`, returning the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Triangular<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> IntoIterator for Triangular<T>
+where
+    T: IntoIterator,
+{
+    type Item = <T as IntoIterator>::Item;
+    type IntoIter = <T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Triangular<T>
+where
+    &'a T: IntoIterator,
+{
+    type Item = <&'a T as IntoIterator>::Item;
+    type IntoIter = <&'a T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<T> Triangular<T>
+where
+    for<'a> &'a T: IntoIterator<Item = &'a f64>,
+{
+    /// Returns the triangular discrimination between two points.
+    ///
+    /// This is the sum of (point1 - point2)^2 / (point1 + point2) for each dimension, skipping dimensions where both are zero.
+    pub fn discrimination(&self, rhs: &Triangular<T>) -> f64 {
+        self.0
+            .into_iter()
+            .zip(&rhs.0)
+            .filter(|(x, y)| *x + *y != 0.0)
+            .map(|(x, y)| (x - y).powi(2) / (x + y))
+            .sum()
+    }
+}
+
+impl<T> Metric for Triangular<T>
+where
+    for<'a> &'a T: IntoIterator<Item = &'a f64>,
+    T: Clone,
+{
+    const SUPERMETRIC: Option<FourPoint<Self>> = Some(FourPoint::new());
+
+    fn distance(&self, rhs: &Triangular<T>) -> f64 {
+        self.discrimination(rhs).sqrt()
+    }
+}
+
+impl<T> SuperMetric for Triangular<T>
+where
+    for<'a> &'a T: IntoIterator<Item = &'a f64>,
+    T: Clone,
+{
+}
+
+#[cfg(feature = "serde")]
+impl<T> Serialize for Triangular<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> Deserialize<'de> for Triangular<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Triangular::new(T::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::tests::{colors, histogram_axioms, range_search};
+
+    #[test]
+    fn triangular_3d() {
+        let point1: Triangular<[f64; 3]> = Triangular::new([0.5, 0.5, 0.0]);
+        let point2: Triangular<[f64; 3]> = Triangular::new([1.0, 0.0, 0.0]);
+
+        assert_eq!(point1.discrimination(&point2), 0.25 / 1.5 + 0.25 / 0.5);
+        assert_eq!(
+            point1.distance(&point2),
+            point1.discrimination(&point2).sqrt()
+        );
+        assert_eq!(point1.distance(&point1), 0.0);
+    }
+
+    #[test]
+    fn triangular_axioms() {
+        histogram_axioms(Triangular::new);
+    }
+
+    #[test]
+    fn sisap_colors_triangular() {
+        let points = colors()
+            .into_iter()
+            .step_by(10)
+            .map(Triangular::new)
+            .collect();
+        range_search(points, 7044, 0.93);
+    }
+}
@@ -1,20 +1,62 @@
-use crate::builder::Builder;
-use crate::exclusions::{BallExclusion, ExclusionSync, SheetExclusion};
+use crate::builder::{BuildError, Builder, Offset, Radii, Selection};
+use crate::cull;
+use crate::exclusions::{Exclusion, Zone};
+use crate::knn;
 use crate::metric::Metric;
+use crate::{QueryStats, RangeStats};
 
 use bitvec::prelude::*;
-use itertools::{Either, Itertools};
+use itertools::Either;
 use rayon::prelude::*;
+use roaring::{MultiOps, RoaringBitmap};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
-    fs::File,
+    borrow::Cow,
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    ops::Range,
     path::{Path, PathBuf},
+    time::Instant,
 };
 use thiserror::Error;
+use wide::u64x4;
+
+/// Version of the on-disk format, incremented whenever it changes incompatibly.
+const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.bincode";
+const ZONES: &str = "zones.bincode";
+const DATASET: &str = "dataset.bincode";
+const MAPPED_DATASET: &str = "dataset.points";
+const TOMBSTONES: &str = "tombstones.bincode";
+
+/// Magic number at the start of a [container](crate::Builder::container).
+const CONTAINER_MAGIC: [u8; 8] = *b"BITPART\0";
+/// Size of the fixed part at the start of a container: the magic number, the format version as a `u32`, the checksum of the
+/// header as a `u32`, and the length of the header as a `u64`. All are little-endian.
+const CONTAINER_PREAMBLE: usize = 24;
+
+/// Bitvectors are stored in `u64` words, matching the layout of [`ColumnEncoding::Bitvec`] columns on disk.
+type BitVec = bitvec::vec::BitVec<u64, Lsb0>;
+type BitSlice = bitvec::slice::BitSlice<u64, Lsb0>;
+
+/// Magic number at the start of every [`ColumnEncoding::Bitvec`] column.
+const COLUMN_MAGIC: [u8; 4] = *b"BPBV";
+/// Size of the header of a [`ColumnEncoding::Bitvec`] column: the magic number, the value `1` as a `u32` to record the byte order
+/// of the words, and the number of bits as a `u64`, padded with zeroes to [`COLUMN_ALIGN`]. Blocks start at this offset, so they
+/// are aligned in a memory map.
+const COLUMN_HEADER: usize = 32;
+/// Alignment of the blocks of a [`ColumnEncoding::Bitvec`] column, and of every column in a container.
+const COLUMN_ALIGN: usize = std::mem::align_of::<u64x4>();
+/// Number of bits in each block of a [`ColumnEncoding::Bitvec`] column, matching the blocks of a [`bitvec_simd::BitVec`].
+const BLOCK_BITS: usize = 256;
 
 /// On-disk BitPart.
 ///
 /// BitPart variant which stores partitioning data on disk. Instead of holding a vector of bitsets in memory, this struct
-/// holds a vector of [`Mmap`](memmap2::Mmap)s and only deserializes the relevant columns into memory at query-time.
+/// holds a vector of [`Mmap`](memmap2::Mmap)s and only reads the relevant columns at query-time.
 ///
 /// The BitPart data structure consists of three components: the dataset itself, information about exclusion zones, and a
 /// vector of bitsets which represent the partitioning data for each point and exclusion zone. For a given query,
@@ -25,83 +67,432 @@ use thiserror::Error;
 /// As a benchmark figure, on the default setting of 40 reference points, `40 * 5 = 200` balls and `40c2 = 780` plane exclusions are made.
 /// At 20 dimensions, each point requires `20 * 64 = 1280` bits of storage plus `200 + 780 = 980` bits of partitioning data.
 ///
-/// Unlike [`Sequential`](crate::Sequential) and [`Parallel`](crate::Parallel), this struct usees [`bitvec`](bitvec::vec::BitVec)
-/// bitvectors. They are not SIMD-optimised so expect worse performance in addition to the overhead from memory mapping (IO and deser).
+/// Uncompressed columns are laid out in the same 256-bit blocks as the [`bitvec_simd`] bitvectors of [`Sequential`](crate::Sequential)
+/// and [`Parallel`](crate::Parallel), and are read in place from the memory map. A query only touches the pages of the columns it
+/// needs, and filters them with the same SIMD operations as `Parallel`.
 ///
 /// `Disk` is parallelised.
-pub struct Disk<'a, T> {
-    dataset: Vec<T>,
-    exclusions: Vec<Box<dyn ExclusionSync<T> + 'a>>,
-    bitset: Vec<memmap2::Mmap>,
+///
+/// Alongside the partitioning data, the directory holds the dataset, the definitions of the exclusion zones and a manifest,
+/// so that the data structure can be [reopened](Disk::open) later. As a result, points must implement [`Serialize`] and [`Deserialize`].
+///
+/// By default the dataset is also held in memory. For large datasets of fixed-size points, it can instead be memory mapped with
+/// [`map_dataset`](crate::Builder::map_dataset).
+///
+/// Instead of a directory, the whole index can be stored in a single checksummed file with [`container`](crate::Builder::container).
+///
+/// Columns are stored as uncompressed bitvectors by default. See [`ColumnEncoding`] for a compressed alternative.
+pub struct Disk<T> {
+    dataset: Dataset<T>,
+    exclusions: Vec<Zone<T>>,
+    bitset: Columns,
+    tombstones: BitVec,
+    /// The tombstones as a roaring bitmap for [`ColumnEncoding::Roaring`] columns, kept up to date so that queries need not
+    /// convert them.
+    removed: RoaringBitmap,
+    block_size: usize,
+    path: PathBuf,
+    manifest: Manifest,
+}
+
+/// How [`Disk`] encodes each bitset column on disk.
+///
+/// Set with [`column_encoding`](crate::Builder::column_encoding).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColumnEncoding {
+    /// Uncompressed bitvectors, stored as a small header followed by the 256-bit blocks of a [`bitvec_simd::BitVec`]. Columns are
+    /// read in place from the memory map, without copying, and filtered with SIMD operations in blocks of
+    /// [`block_size`](crate::Builder::build_on_disk) points.
+    #[default]
+    Bitvec,
+    /// Compressed [roaring bitmaps](roaring::RoaringBitmap), with runs of set bits stored as run-length containers.
+    ///
+    /// Columns for zones which contain very few or almost all points take a fraction of the space of a bitvector, and
+    /// intersections and unions are computed on the compressed containers. Roaring bitmaps hold at most `u32::MAX` points.
+    Roaring,
+}
+
+impl ColumnEncoding {
+    /// Returns an error if a column with this encoding cannot hold `len` points.
+    fn check(self, len: usize) -> Result<(), DiskError> {
+        match self {
+            ColumnEncoding::Roaring if u32::try_from(len).is_err() => Err(DiskError::Capacity(len)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Encodes the partitioning data `bits` to `writer`.
+    fn encode<W: Write>(self, bits: &BitSlice, writer: W) -> Result<(), DiskError> {
+        match self {
+            ColumnEncoding::Bitvec => Blocks::encode(bits, writer),
+            ColumnEncoding::Roaring => RoaringBitmap::encode(bits, writer),
+        }
+    }
+
+    /// Decodes a column of `len` points into a bitvector.
+    fn decode(self, bytes: &[u8], len: usize) -> Result<BitVec, DiskError> {
+        match self {
+            ColumnEncoding::Bitvec => Ok(Blocks::decode(bytes)?.bits().to_bitvec()),
+            ColumnEncoding::Roaring => {
+                let mut bv = BitVec::repeat(false, len);
+                for idx in &RoaringBitmap::decode(bytes)? {
+                    bv.set(idx as usize, true);
+                }
+                Ok(bv)
+            }
+        }
+    }
+
+    /// Returns the bitmap of removed points which [`Column::filter`] is given for this encoding. Only roaring columns use it,
+    /// so it is left empty for bitvector columns, which may hold more points than a roaring bitmap.
+    fn removed(self, tombstones: &BitSlice) -> RoaringBitmap {
+        match self {
+            ColumnEncoding::Bitvec => RoaringBitmap::new(),
+            ColumnEncoding::Roaring => roaring_from(tombstones),
+        }
+    }
+
+    /// Returns an error unless `bytes` decode to a column of `len` points.
+    fn validate(self, bytes: &[u8], len: usize) -> Result<(), DiskError> {
+        let valid = match self {
+            ColumnEncoding::Bitvec => Blocks::decode(bytes)?.len == len,
+            ColumnEncoding::Roaring => {
+                RoaringBitmap::decode(bytes)?
+                    .max()
+                    .map_or(0, |max| max as usize + 1)
+                    <= len
+            }
+        };
+
+        match valid {
+            true => Ok(()),
+            false => Err(DiskError::Inconsistent),
+        }
+    }
+}
+
+/// A bitset column, as it is read from the bytes of its file at query-time.
+trait Column<'a>: Sized + Send + Sync {
+    fn decode(bytes: &'a [u8]) -> Result<Self, DiskError>;
+
+    fn encode<W: Write>(bits: &BitSlice, writer: W) -> Result<(), DiskError>;
+
+    /// Returns the indices of points which are in every column of `ins`, in no column of `outs`, and have not been removed.
+    ///
+    /// Removed points are given both as `tombstones` and as the `removed` bitmap, so that each encoding can use its own layout.
+    fn filter(
+        ins: &[&Self],
+        outs: &[&Self],
+        tombstones: &BitVec,
+        removed: &RoaringBitmap,
+        block_size: usize,
+    ) -> Vec<usize>;
+}
+
+/// An uncompressed column, borrowed from its memory map.
+struct Blocks<'a> {
+    blocks: &'a [u64x4],
+    len: usize,
+}
+
+impl<'a> Blocks<'a> {
+    /// Returns the words of the column, including the zero words which pad its last block.
+    fn words(&self) -> &'a [u64] {
+        // SAFETY: `u64x4` is four `u64`s, so every block is exactly four aligned words
+        let (_, words, _) = unsafe { self.blocks.align_to::<u64>() };
+        words
+    }
+
+    /// Returns the bits of the column.
+    fn bits(&self) -> &'a BitSlice {
+        &BitSlice::from_slice(self.words())[..self.len]
+    }
+}
+
+impl<'a> Column<'a> for Blocks<'a> {
+    fn decode(bytes: &'a [u8]) -> Result<Self, DiskError> {
+        if bytes.len() < COLUMN_HEADER
+            || bytes[..4] != COLUMN_MAGIC
+            || bytes[4..8] != 1u32.to_ne_bytes()
+        {
+            return Err(DiskError::Inconsistent);
+        }
+        let len = u64::from_ne_bytes(bytes[8..16].try_into().unwrap()) as usize;
+
+        // Memory maps are page-aligned, so the blocks are always aligned
+        let (prefix, blocks, suffix) = unsafe { bytes[COLUMN_HEADER..].align_to::<u64x4>() };
+        if !prefix.is_empty() || !suffix.is_empty() || blocks.len() != len.div_ceil(BLOCK_BITS) {
+            return Err(DiskError::Inconsistent);
+        }
+
+        Ok(Blocks { blocks, len })
+    }
+
+    fn encode<W: Write>(bits: &BitSlice, mut writer: W) -> Result<(), DiskError> {
+        let mut bv = bits.to_bitvec();
+        bv.set_uninitialized(false);
+        // Pad the last block with zero words
+        let mut words = bv.into_vec();
+        words.resize(bits.len().div_ceil(BLOCK_BITS) * 4, 0);
+
+        writer.write_all(&COLUMN_MAGIC)?;
+        writer.write_all(&1u32.to_ne_bytes())?;
+        writer.write_all(&(bits.len() as u64).to_ne_bytes())?;
+        writer.write_all(&[0; COLUMN_HEADER - 16])?;
+        for word in words {
+            writer.write_all(&word.to_ne_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    fn filter(
+        ins: &[&Self],
+        outs: &[&Self],
+        tombstones: &BitVec,
+        _removed: &RoaringBitmap,
+        block_size: usize,
+    ) -> Vec<usize> {
+        let len = tombstones.len();
+        let removed = tombstones.as_raw_slice();
+        // Blocks of points are rounded up to whole SIMD blocks
+        let step = block_size.div_ceil(BLOCK_BITS).max(1);
+
+        (0..len.div_ceil(BLOCK_BITS))
+            .into_par_iter()
+            .step_by(step)
+            .flat_map(|from| {
+                let to = len.div_ceil(BLOCK_BITS).min(from + step);
+
+                let mut ands = vec![u64x4::splat(u64::MAX); to - from];
+                for column in ins {
+                    ands.iter_mut()
+                        .zip(&column.blocks[from..to])
+                        .for_each(|(acc, block)| *acc &= *block);
+                }
+
+                // Removed points are excluded from every query
+                let mut nots = removed[from * 4..removed.len().min(to * 4)]
+                    .chunks(4)
+                    .map(|words| {
+                        let mut block = [0; 4];
+                        block[..words.len()].copy_from_slice(words);
+                        u64x4::new(block)
+                    })
+                    .collect::<Vec<_>>();
+                for column in outs {
+                    nots.iter_mut()
+                        .zip(&column.blocks[from..to])
+                        .for_each(|(acc, block)| *acc |= *block);
+                }
+
+                let res = ands
+                    .into_iter()
+                    .zip(nots)
+                    .flat_map(|(ands, nots)| (ands & !nots).to_array())
+                    .collect::<Vec<_>>();
+
+                // The last block may hold bits past the end of the dataset
+                BitSlice::from_slice(&res)
+                    .iter_ones()
+                    .map(|idx| from * BLOCK_BITS + idx)
+                    .take_while(|idx| *idx < len)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+impl Column<'_> for RoaringBitmap {
+    fn decode(bytes: &[u8]) -> Result<Self, DiskError> {
+        Ok(RoaringBitmap::deserialize_from(bytes)?)
+    }
+
+    fn encode<W: Write>(bits: &BitSlice, writer: W) -> Result<(), DiskError> {
+        let mut bitmap = roaring_from(bits);
+        bitmap.optimize();
+        Ok(bitmap.serialize_into(writer)?)
+    }
+
+    fn filter(
+        ins: &[&Self],
+        outs: &[&Self],
+        tombstones: &BitVec,
+        removed: &RoaringBitmap,
+        _block_size: usize,
+    ) -> Vec<usize> {
+        let ands = match ins.is_empty() {
+            true => {
+                let mut all = RoaringBitmap::new();
+                all.insert_range(0..tombstones.len() as u32);
+                all
+            }
+            false => ins.iter().copied().intersection(),
+        };
+
+        // Removed points are excluded from every query
+        let nots = outs.iter().copied().union() | removed;
+
+        (ands - nots).into_iter().map(|idx| idx as usize).collect()
+    }
+}
+
+/// Converts a bitslice into a roaring bitmap. The length of `bits` must already have been [checked](ColumnEncoding::check).
+fn roaring_from(bits: &BitSlice) -> RoaringBitmap {
+    RoaringBitmap::from_sorted_iter(bits.iter_ones().map(|idx| idx as u32)).unwrap()
+}
+
+/// Storage for the bitset columns of a [`Disk`].
+enum Columns {
+    /// One memory mapped file per column, in a directory.
+    Files(Vec<memmap2::Mmap>),
+    /// Every column in a single memory mapped [container](crate::Builder::container), with the byte range and checksum of each column,
+    /// and the offset of the tombstones.
+    Container {
+        mmap: memmap2::Mmap,
+        ranges: Vec<Range<usize>>,
+        checksums: Vec<u32>,
+        tombstones: u64,
+    },
+}
+
+impl Columns {
+    fn len(&self) -> usize {
+        match self {
+            Columns::Files(mmaps) => mmaps.len(),
+            Columns::Container { ranges, .. } => ranges.len(),
+        }
+    }
+
+    /// Returns the bytes of the column at `index`.
+    ///
+    /// # Panics
+    /// This function will panic if `index` is out of bounds.
+    fn get(&self, index: usize) -> &[u8] {
+        match self {
+            Columns::Files(mmaps) => &mmaps[index],
+            Columns::Container { mmap, ranges, .. } => &mmap[ranges[index].clone()],
+        }
+    }
+}
+
+/// Entry in the column table of a container. Offsets are relative to the start of the column data.
+#[derive(Serialize, Deserialize)]
+struct ColumnEntry {
+    offset: u64,
+    len: u64,
+    checksum: u32,
+}
+
+/// Header of a container: the manifest, exclusion zones, dataset and column table.
+///
+/// The tombstones follow the header as `u64` words, outside the checksum, so that a [removal](Disk::remove) only overwrites
+/// the word holding its tombstone.
+type Header<T> = (Manifest, Vec<Option<Zone<T>>>, Vec<T>, Vec<ColumnEntry>);
+
+/// Describes the contents of a directory written by [`Disk`].
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    len: usize,
+    zones: usize,
+    /// Size of each point in a memory mapped dataset, or `None` if the dataset is held in memory.
+    stride: Option<usize>,
+    params: Params,
+}
+
+/// Parameters of the [`Builder`] used to build a [`Disk`].
+#[derive(Serialize, Deserialize)]
+struct Params {
     block_size: usize,
+    column_encoding: ColumnEncoding,
+    container: bool,
+    ref_points: u64,
+    selection: Selection,
+    radii: Radii,
+    radii_per_point: usize,
+    mean_distance: f64,
+    radius_increment: f64,
+    offset: Offset,
+    sample_size: usize,
+    four_point: bool,
+    generate_exclusions: bool,
+    custom_exclusions: usize,
+    cull_popcnt: Option<f64>,
+    cull_similarity: Option<f64>,
+    cull_independent: Option<usize>,
 }
 
-impl<T> crate::BitPart<T> for Disk<'_, T>
+impl<T> crate::BitPart<T> for Disk<T>
 where
-    T: Metric + Send + Sync,
+    T: Metric + Send + Sync + Serialize + DeserializeOwned,
 {
     type Error = DiskError;
 
     fn range_search(&self, point: T, threshold: f64) -> Result<Vec<(T, f64)>, Self::Error> {
-        let (ins, outs): (Vec<usize>, Vec<usize>) = self
-            .exclusions
-            .par_iter()
-            .enumerate()
-            .filter_map(|(idx, ez)| {
-                if ez.must_be_in(&point, threshold) {
-                    Some(Either::Left(idx))
-                } else if ez.must_be_out(&point, threshold) {
-                    Some(Either::Right(idx))
-                } else {
-                    None
-                }
-            })
-            .partition_map(|x| x);
-
-        let ins = ins
+        self.range_search_indices(point, threshold)?
             .into_par_iter()
-            .map(|idx| bincode::deserialize::<BitVec>(self.bitset.get(idx).unwrap()))
-            .collect::<Result<Vec<_>, bincode::Error>>()?;
+            .map(|(idx, d)| Ok((self.dataset.point(idx)?.into_owned(), d)))
+            .collect()
+    }
 
-        let outs = outs
+    fn range_search_indices(
+        &self,
+        point: T,
+        threshold: f64,
+    ) -> Result<Vec<(usize, f64)>, Self::Error> {
+        self.candidates(&point, threshold)?
             .into_par_iter()
-            .map(|idx| bincode::deserialize::<BitVec>(self.bitset.get(idx).unwrap()))
-            .collect::<Result<Vec<_>, bincode::Error>>()?;
-
-        let res = self
-            .dataset
-            .par_chunks(self.block_size)
-            .enumerate()
-            .flat_map(|(blk_idx, points)| {
-                let from = blk_idx * self.block_size;
-                let to = (blk_idx * self.block_size) + points.len();
-
-                let blk_ins = ins.iter().map(|bv| &bv[from..to]).collect::<Vec<_>>();
-                let blk_outs = outs.iter().map(|bv| &bv[from..to]).collect::<Vec<_>>();
+            .map(|idx| Ok((idx, point.distance(&*self.dataset.point(idx)?))))
+            .filter(|res| !matches!(res, Ok((_, d)) if *d > threshold))
+            .collect()
+    }
 
-                let len = points.len();
+    fn range_search_stats(&self, point: T, threshold: f64) -> Result<RangeStats, Self::Error> {
+        let start = Instant::now();
+        let (ins, outs) = self.partition(&point, threshold);
+        let mut stats = QueryStats::new(&self.exclusions, &ins, &outs, start.elapsed());
 
-                let ands = blk_ins
-                    .into_iter()
-                    .fold(BitVec::repeat(true, len), |acc: BitVec, v| acc & v);
+        let start = Instant::now();
+        let candidates = self.filter_columns(&ins, &outs)?;
+        stats.filter_time = start.elapsed();
+        stats.candidates = candidates.len();
 
-                let nots = !blk_outs
-                    .into_iter()
-                    .fold(BitVec::repeat(false, len), |acc: BitVec, v| acc | v);
+        let start = Instant::now();
+        let res = candidates
+            .into_par_iter()
+            .map(|idx| Ok((idx, point.distance(&*self.dataset.point(idx)?))))
+            .filter(|res| !matches!(res, Ok((_, d)) if *d > threshold))
+            .collect::<Result<Vec<_>, DiskError>>()?;
+        stats.distance_time = start.elapsed();
+        stats.results = res.len();
 
-                let res = ands & nots;
+        Ok((res, stats))
+    }
 
-                res.iter_ones().map(|idx| &points[idx]).collect::<Vec<_>>()
-            })
-            .map(|pt| (pt.clone(), point.distance(pt)))
-            .filter(|(_, d)| *d <= threshold)
-            .collect::<Vec<_>>();
+    fn knn_search(&self, point: T, k: usize) -> Result<Vec<(T, f64)>, Self::Error> {
+        self.knn_search_indices(point, k)?
+            .into_par_iter()
+            .map(|(idx, d)| Ok((self.dataset.point(idx)?.into_owned(), d)))
+            .collect()
+    }
 
-        Ok(res)
+    fn knn_search_indices(&self, point: T, k: usize) -> Result<Vec<(usize, f64)>, Self::Error> {
+        knn::search(
+            k,
+            self.dataset.len(),
+            |idx| self.tombstones[idx],
+            |radius| self.candidates(&point, radius),
+            |indices| {
+                indices
+                    .par_iter()
+                    .map(|&idx| Ok(point.distance(&*self.dataset.point(idx)?)))
+                    .collect()
+            },
+        )
     }
 
     fn len(&self) -> usize {
-        self.dataset.len()
+        self.dataset.len() - self.tombstones.count_ones()
     }
 
     fn zones(&self) -> usize {
@@ -109,14 +500,13 @@ where
     }
 
     fn is_empty(&self) -> bool {
-        self.dataset.len() == 0
+        self.len() == 0
     }
 }
 
-impl<'a, T> Disk<'a, T>
+impl<T> Disk<T>
 where
-    T: Metric + Send + Sync,
-    dyn ExclusionSync<T>: 'a,
+    T: Metric + Send + Sync + Serialize + DeserializeOwned,
 {
     pub(crate) fn setup<P>(
         builder: Builder<T>,
@@ -124,101 +514,1092 @@ where
         block_size: Option<usize>,
     ) -> Result<Self, DiskError>
     where
-        P: AsRef<Path> + 'a,
+        P: AsRef<Path>,
     {
         let block_size = block_size.unwrap_or(builder.dataset.len());
         let path = path.as_ref().to_owned();
-        // TODO: actually randomise this
-        let ref_points = &builder.dataset[0..(builder.ref_points as usize)];
-        let mut exclusions = Self::ball_exclusions(&builder, ref_points);
-        exclusions.extend(Self::sheet_exclusions(&builder, ref_points));
-        let bitset = Self::make_bitset(block_size, &builder, path, &exclusions)?;
-        Ok(Self {
-            dataset: builder.dataset,
+        builder.column_encoding.check(builder.dataset.len())?;
+        if builder.container && builder.map_dataset {
+            return Err(DiskError::MappedContainer);
+        }
+
+        if builder.container && path.exists() {
+            return Err(io::Error::from(io::ErrorKind::AlreadyExists).into());
+        }
+        let exclusions = builder.zones();
+
+        let columns = exclusions
+            .par_iter()
+            .map(|ez| Self::make_column(&builder.dataset, builder.column_encoding, ez))
+            .collect::<Result<Vec<_>, DiskError>>()?;
+
+        let (bitset, columns) = match builder.container {
+            // A container is written by `persist`, once everything else is known
+            true => (Columns::Files(vec![]), Some(columns)),
+            false => {
+                write(&path, ZONES, &definitions(&exclusions))?;
+                let bitset = columns
+                    .into_par_iter()
+                    .enumerate()
+                    .map(|(idx, column)| {
+                        let path = path.join(format!("{}.column", idx));
+                        fs::write(&path, column)?;
+                        unsafe { Ok(memmap2::Mmap::map(&File::open(path)?)?) }
+                    })
+                    .collect::<Result<Vec<_>, DiskError>>()?;
+                (Columns::Files(bitset), None)
+            }
+        };
+
+        let dataset = match builder.map_dataset {
+            true => Dataset::mapped(&path, &builder.dataset)?,
+            false => Dataset::Memory(builder.dataset),
+        };
+
+        let manifest = Manifest {
+            len: dataset.len(),
+            zones: exclusions.len(),
+            stride: dataset.stride(),
+            params: Params {
+                block_size,
+                column_encoding: builder.column_encoding,
+                container: builder.container,
+                ref_points: builder.ref_points,
+                selection: builder.selection,
+                radii: builder.radii,
+                radii_per_point: builder.radii_per_point,
+                mean_distance: builder.mean_distance,
+                radius_increment: builder.radius_increment,
+                offset: builder.offset,
+                sample_size: builder.sample_size,
+                four_point: builder.four_point,
+                generate_exclusions: builder.generate_exclusions,
+                custom_exclusions: builder.custom_exclusions.len(),
+                cull_popcnt: builder.cull_popcnt,
+                cull_similarity: builder.cull_similarity,
+                cull_independent: builder.cull_independent,
+            },
+        };
+
+        let mut disk = Self {
+            tombstones: BitVec::repeat(false, dataset.len()),
+            removed: RoaringBitmap::new(),
+            dataset,
             bitset,
             exclusions,
             block_size,
+            path,
+            manifest,
+        };
+        disk.persist(columns)?;
+
+        Ok(disk)
+    }
+
+    /// Open a `Disk` previously built with [`build_on_disk`](crate::Builder::build_on_disk) in the directory or
+    /// [container](crate::Builder::container) `path`.
+    ///
+    /// Removed points which had not been [compacted](Disk::compact) remain removed. The index is [verified](Disk::verify)
+    /// before it is returned, which reads every column.
+    ///
+    /// # Errors
+    /// This function will return:
+    /// - [`DiskError::Missing`] if any of the files in the directory are missing.
+    /// - [`DiskError::Version`] if the directory was written by an incompatible version of this library.
+    /// - [`DiskError::CustomZones`] if the `Disk` was built with [custom exclusion zones](crate::Builder::exclusion).
+    /// - [`DiskError::Inconsistent`] if the files in the directory do not match one another, or a column is truncated.
+    /// - [`DiskError::Truncated`] or [`DiskError::Corrupt`] if a container has been truncated or corrupted.
+    pub fn open<P>(path: P) -> Result<Self, DiskError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_owned();
+        if path.is_file() {
+            return Self::open_container(path);
+        }
+
+        let mut file = BufReader::new(open_file(&path.join(MANIFEST))?);
+        let version = bincode::deserialize_from::<_, u32>(&mut file)?;
+        if version != FORMAT_VERSION {
+            return Err(DiskError::Version {
+                found: version,
+                expected: FORMAT_VERSION,
+            });
+        }
+        let manifest = bincode::deserialize_from::<_, Manifest>(&mut file)?;
+
+        let exclusions = read::<Vec<Option<Zone<T>>>>(&path, ZONES)?
+            .into_iter()
+            .map(|ez| ez.ok_or(DiskError::CustomZones))
+            .collect::<Result<Vec<_>, _>>()?;
+        let dataset = match manifest.stride {
+            Some(stride) => {
+                let file = open_file(&path.join(MAPPED_DATASET))?;
+                let mmap = unsafe { memmap2::Mmap::map(&file)? };
+                if mmap.len() != stride * manifest.len {
+                    return Err(DiskError::Inconsistent);
+                }
+
+                Dataset::Mapped {
+                    mmap,
+                    stride,
+                    len: manifest.len,
+                    _marker: PhantomData,
+                }
+            }
+            None => Dataset::Memory(read::<Vec<T>>(&path, DATASET)?),
+        };
+        let tombstones = read::<BitVec>(&path, TOMBSTONES)?;
+
+        if exclusions.len() != manifest.zones
+            || dataset.len() != manifest.len
+            || tombstones.len() != manifest.len
+        {
+            return Err(DiskError::Inconsistent);
+        }
+
+        let bitset = (0..exclusions.len())
+            .map(|idx| {
+                let file = open_file(&path.join(format!("{}.column", idx)))?;
+                unsafe { Ok(memmap2::Mmap::map(&file)?) }
+            })
+            .collect::<Result<Vec<_>, DiskError>>()?;
+
+        let disk = Self {
+            dataset,
+            exclusions,
+            bitset: Columns::Files(bitset),
+            removed: manifest.params.column_encoding.removed(&tombstones),
+            tombstones,
+            block_size: manifest.params.block_size,
+            path,
+            manifest,
+        };
+        disk.verify()?;
+
+        Ok(disk)
+    }
+
+    fn open_container(path: PathBuf) -> Result<Self, DiskError> {
+        let mmap = unsafe { memmap2::Mmap::map(&open_file(&path)?)? };
+        if mmap.len() < CONTAINER_PREAMBLE {
+            return Err(DiskError::Truncated);
+        }
+        if mmap[..8] != CONTAINER_MAGIC {
+            return Err(DiskError::Corrupt);
+        }
+        let version = u32::from_le_bytes(mmap[8..12].try_into().unwrap());
+        if version != FORMAT_VERSION {
+            return Err(DiskError::Version {
+                found: version,
+                expected: FORMAT_VERSION,
+            });
+        }
+        let checksum = u32::from_le_bytes(mmap[12..16].try_into().unwrap());
+        let header_len =
+            u64::from_le_bytes(mmap[16..CONTAINER_PREAMBLE].try_into().unwrap()) as usize;
+
+        let header = mmap
+            .get(CONTAINER_PREAMBLE..CONTAINER_PREAMBLE + header_len)
+            .ok_or(DiskError::Truncated)?;
+        if crc32fast::hash(header) != checksum {
+            return Err(DiskError::Corrupt);
+        }
+        let (manifest, zones, points, entries) = bincode::deserialize::<Header<T>>(header)?;
+
+        let exclusions = zones
+            .into_iter()
+            .map(|ez| ez.ok_or(DiskError::CustomZones))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if exclusions.len() != manifest.zones
+            || entries.len() != manifest.zones
+            || points.len() != manifest.len
+        {
+            return Err(DiskError::Inconsistent);
+        }
+
+        let offset = (CONTAINER_PREAMBLE + header_len).next_multiple_of(COLUMN_ALIGN);
+        let words = manifest.len.div_ceil(u64::BITS as usize);
+        let tombstones = mmap
+            .get(offset..offset + words * 8)
+            .ok_or(DiskError::Truncated)?
+            .chunks_exact(8)
+            .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        let mut tombstones = BitVec::from_vec(tombstones);
+        if tombstones[manifest.len..].any() {
+            return Err(DiskError::Inconsistent);
+        }
+        tombstones.truncate(manifest.len);
+
+        let data = (offset + words * 8).next_multiple_of(COLUMN_ALIGN);
+        let ranges = entries
+            .iter()
+            .map(|entry| {
+                let from = data + entry.offset as usize;
+                let to = from + entry.len as usize;
+                match (from % COLUMN_ALIGN, to <= mmap.len()) {
+                    (0, true) => Ok(from..to),
+                    (0, false) => Err(DiskError::Truncated),
+                    _ => Err(DiskError::Corrupt),
+                }
+            })
+            .collect::<Result<Vec<_>, DiskError>>()?;
+        let checksums = entries.iter().map(|entry| entry.checksum).collect();
+
+        let disk = Self {
+            dataset: Dataset::Memory(points),
+            exclusions,
+            bitset: Columns::Container {
+                mmap,
+                ranges,
+                checksums,
+                tombstones: offset as u64,
+            },
+            removed: manifest.params.column_encoding.removed(&tombstones),
+            tombstones,
+            block_size: manifest.params.block_size,
+            path,
+            manifest,
+        };
+        disk.verify()?;
+
+        Ok(disk)
+    }
+
+    /// Check every column for truncation or corruption, without running a query.
+    ///
+    /// Every column is decoded and checked against the length of the dataset. Columns in a [container](crate::Builder::container)
+    /// are also checked against their checksums; a directory has no checksums, so corruption which leaves a column decodable
+    /// is only detected in a container. The tombstones of a container are updated in place by [`remove`](Disk::remove), so they
+    /// are not covered by a checksum.
+    ///
+    /// # Errors
+    /// This function will return:
+    /// - [`DiskError::Corrupt`] if the checksum of a column does not match.
+    /// - [`DiskError::Inconsistent`] if a column cannot be decoded, or does not match the length of the dataset.
+    pub fn verify(&self) -> Result<(), DiskError> {
+        let encoding = self.manifest.params.column_encoding;
+
+        (0..self.bitset.len()).into_par_iter().try_for_each(|idx| {
+            let bytes = self.bitset.get(idx);
+            if let Columns::Container { checksums, .. } = &self.bitset {
+                if crc32fast::hash(bytes) != checksums[idx] {
+                    return Err(DiskError::Corrupt);
+                }
+            }
+
+            encoding.validate(bytes, self.dataset.len())
+        })
+    }
+
+    /// Returns the point at `index` in the dataset, or `None` if it is out of bounds or has been [removed](Disk::remove).
+    ///
+    /// Indices are those returned by [`range_search_indices`](crate::BitPart::range_search_indices) and
+    /// [`knn_search_indices`](crate::BitPart::knn_search_indices), and match the order in which points were given to the [`Builder`].
+    ///
+    /// If the dataset is [memory mapped](crate::Builder::map_dataset), the point is deserialized and returned by value.
+    pub fn get(&self, index: usize) -> Result<Option<Cow<'_, T>>, DiskError> {
+        match self.tombstones.get(index).as_deref() {
+            Some(false) => Ok(Some(self.dataset.point(index)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Insert a point into the dataset. Its index follows every existing point, including removed points which have not yet been
+    /// [compacted](Disk::compact).
+    ///
+    /// The point is partitioned using the existing exclusion zones, so it is immediately visible to queries.
+    /// Exclusion zones are not regenerated; if inserted points are distributed very differently to the original dataset,
+    /// the zones may partition them poorly and queries will slow down.
+    ///
+    /// Every column is rewritten on disk, so prefer [`extend`](Disk::extend) when inserting many points.
+    pub fn insert(&mut self, point: T) -> Result<(), DiskError> {
+        self.extend(std::iter::once(point))
+    }
+
+    /// Insert every point in `points` into the dataset, in order.
+    ///
+    /// See [`insert`](Disk::insert) for details.
+    pub fn extend(&mut self, points: impl IntoIterator<Item = T>) -> Result<(), DiskError> {
+        let points = points.into_iter().collect::<Vec<_>>();
+        self.dataset.check(&points)?;
+        self.manifest
+            .params
+            .column_encoding
+            .check(self.dataset.len() + points.len())?;
+
+        let columns =
+            self.rewrite_columns(|ez, bv| bv.extend(points.iter().map(|pt| ez.is_in(pt))))?;
+
+        self.tombstones
+            .resize(self.dataset.len() + points.len(), false);
+        self.dataset.extend(&self.path, points)?;
+        self.persist(columns)
+    }
+
+    /// Remove the point at `index` from the dataset, returning `false` if it is out of bounds or was already removed.
+    ///
+    /// The point is marked with a tombstone and excluded from every query, but its partitioning data remains on disk until
+    /// [`compact`](Disk::compact) is called. Indices of other points are unchanged.
+    pub fn remove(&mut self, index: usize) -> Result<bool, DiskError> {
+        match self.tombstones.get_mut(index) {
+            Some(mut removed) if !*removed => *removed = true,
+            _ => return Ok(false),
+        }
+
+        // The tombstone is rolled back if it can not be written, so the index never disagrees with the files on disk
+        if let Err(e) = self.write_tombstone(index) {
+            self.tombstones.set(index, false);
+            return Err(e);
+        }
+        if self.manifest.params.column_encoding == ColumnEncoding::Roaring {
+            self.removed.insert(index as u32);
+        }
+        Ok(true)
+    }
+
+    /// Writes the tombstone of the point at `index` to disk.
+    fn write_tombstone(&self, index: usize) -> Result<(), DiskError> {
+        match &self.bitset {
+            // Only the word holding the tombstone is overwritten
+            Columns::Container { tombstones, .. } => {
+                let word = index / u64::BITS as usize;
+                let mut file = OpenOptions::new().write(true).open(&self.path)?;
+                file.seek(SeekFrom::Start(tombstones + word as u64 * 8))?;
+                file.write_all(&self.tombstones.as_raw_slice()[word].to_le_bytes())?;
+                Ok(file.sync_data()?)
+            }
+            Columns::Files(_) => write(&self.path, TOMBSTONES, &self.tombstones),
+        }
+    }
+
+    /// Drop every [removed](Disk::remove) point from the dataset and partitioning data, rewriting every column on disk.
+    ///
+    /// Remaining points keep their relative order, but their indices are shifted down to fill the gaps left by removed points.
+    pub fn compact(&mut self) -> Result<(), DiskError> {
+        let removed = self.tombstones.clone();
+        let columns = self.rewrite_columns(|_, bv| {
+            *bv = bv
+                .iter()
+                .by_vals()
+                .zip(removed.iter().by_vals())
+                .filter_map(|(bit, removed)| (!removed).then_some(bit))
+                .collect();
+        })?;
+
+        self.dataset.compact(&self.path, &removed)?;
+        self.tombstones = BitVec::repeat(false, self.dataset.len());
+        self.removed.clear();
+        self.persist(columns)
+    }
+
+    /// Cull exclusion zones with low exclusion power.
+    /// This function compares every zone with every other zone and calculates the [Hamming distance](https://en.wikipedia.org/wiki/Hamming_distance) between their columns.
+    /// If a zone's similarity ratio to an earlier zone is above the given `threshold`, it is marked for removal.
+    ///
+    /// Zones are compared in parallel and counting stops as soon as a pair is known to be dissimilar. Uncompressed columns are compared
+    /// in place in the memory map, while [roaring](ColumnEncoding::Roaring) columns are decoded into memory first. The files of culled
+    /// columns are deleted, and the remaining columns renumbered. To cull zones before any columns are written, use [`Builder::cull_by_similarity`].
+    pub fn cull_by_similarity(&mut self, threshold: f64) -> Result<(), DiskError> {
+        let words = self.column_words()?;
+        let to_cull = cull::by_similarity(
+            &cull::Columns::single(words.iter().map(|words| &**words), self.dataset.len()),
+            threshold,
+        );
+
+        self.cull(to_cull)
+    }
+
+    /// Cull every exclusion zone except the `keep` most independent zones, instead of culling zones above a fixed similarity threshold.
+    ///
+    /// See [`Builder::cull_to_independent`] for how zones are chosen, and to cull zones before any columns are written. The files of culled
+    /// columns are deleted, and the remaining columns renumbered.
+    pub fn cull_to_independent(&mut self, keep: usize) -> Result<(), DiskError> {
+        let words = self.column_words()?;
+        let to_cull = cull::to_independent(
+            &cull::Columns::single(words.iter().map(|words| &**words), self.dataset.len()),
+            keep,
+        );
+
+        self.cull(to_cull)
+    }
+
+    /// Cull exclusion zones with low exclusion power.
+    /// This function measures the exclusion power of a zone by counting the ratio of points that are in/out to the dataset.
+    /// If either ratio is above the `threshold` given, it is marked for removal.
+    ///
+    /// The files of culled columns are deleted, and the remaining columns renumbered. To cull zones before any columns are
+    /// written, use [`Builder::cull_by_popcnt`].
+    pub fn cull_by_popcnt(&mut self, threshold: f64) -> Result<(), DiskError> {
+        let popcnt = self
+            .column_words()?
+            .iter()
+            .map(|words| BitSlice::from_slice(words)[..self.dataset.len()].count_ones())
+            .collect::<Vec<_>>();
+
+        self.cull(cull::by_popcnt(popcnt, self.dataset.len(), threshold))
+    }
+
+    /// Returns the words of every column. Uncompressed columns are borrowed from the memory map, and others are decoded.
+    fn column_words(&self) -> Result<Vec<Cow<'_, [u64]>>, DiskError> {
+        let encoding = self.manifest.params.column_encoding;
+
+        (0..self.bitset.len())
+            .into_par_iter()
+            .map(|idx| {
+                let bytes = self.bitset.get(idx);
+                Ok(match encoding {
+                    ColumnEncoding::Bitvec => Cow::Borrowed(Blocks::decode(bytes)?.words()),
+                    ColumnEncoding::Roaring => {
+                        Cow::Owned(encoding.decode(bytes, self.dataset.len())?.into_vec())
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn cull(&mut self, to_cull: HashSet<usize>) -> Result<(), DiskError> {
+        if to_cull.is_empty() {
+            return Ok(());
+        }
+
+        let keep = (0..self.exclusions.len())
+            .filter(|idx| !to_cull.contains(idx))
+            .collect::<Vec<_>>();
+        cull::retain(&mut self.exclusions, &to_cull);
+        self.manifest.zones = self.exclusions.len();
+
+        if self.manifest.params.container {
+            let columns = keep
+                .iter()
+                .map(|idx| self.bitset.get(*idx).to_vec())
+                .collect();
+            return self.persist(Some(columns));
+        }
+
+        for idx in &to_cull {
+            fs::remove_file(self.path.join(format!("{}.column", idx)))?;
+        }
+        // Columns are renamed in ascending order, so each is moved into a slot which has already been vacated
+        self.bitset = Columns::Files(
+            keep.into_iter()
+                .enumerate()
+                .map(|(idx, old)| {
+                    let path = self.path.join(format!("{}.column", idx));
+                    if idx != old {
+                        fs::rename(self.path.join(format!("{}.column", old)), &path)?;
+                    }
+                    unsafe { Ok(memmap2::Mmap::map(&File::open(path)?)?) }
+                })
+                .collect::<Result<Vec<_>, DiskError>>()?,
+        );
+
+        write(&self.path, ZONES, &definitions(&self.exclusions))?;
+        self.persist(None)
+    }
+
+    /// Writes the dataset, tombstones and manifest to disk.
+    ///
+    /// A container is rewritten in full, with `columns` if they have been [rewritten](Disk::rewrite_columns) and its existing
+    /// columns otherwise.
+    fn persist(&mut self, columns: Option<Vec<Vec<u8>>>) -> Result<(), DiskError> {
+        self.manifest.len = self.dataset.len();
+
+        if self.manifest.params.container {
+            let columns = match &columns {
+                Some(columns) => columns.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+                None => (0..self.bitset.len())
+                    .map(|idx| self.bitset.get(idx))
+                    .collect(),
+            };
+            let points = self.dataset.memory().ok_or(DiskError::MappedContainer)?;
+
+            self.bitset = write_container(
+                &self.path,
+                &self.manifest,
+                &definitions(&self.exclusions),
+                points,
+                &self.tombstones,
+                &columns,
+            )?;
+            return Ok(());
+        }
+
+        // A memory mapped dataset is written as it changes
+        if let Dataset::Memory(dataset) = &self.dataset {
+            write(&self.path, DATASET, dataset)?;
+        }
+        write(&self.path, TOMBSTONES, &self.tombstones)?;
+        // The manifest is written last, so that it is only present once everything else is.
+        write(&self.path, MANIFEST, &(FORMAT_VERSION, &self.manifest))
+    }
+
+    /// Decodes every column and applies `f` to it.
+    ///
+    /// Columns in a directory are written back to disk. Columns in a container are returned, to be written by [`persist`](Disk::persist).
+    fn rewrite_columns<F>(&mut self, f: F) -> Result<Option<Vec<Vec<u8>>>, DiskError>
+    where
+        F: Fn(&Zone<T>, &mut BitVec) + Sync,
+    {
+        let encoding = self.manifest.params.column_encoding;
+        let len = self.dataset.len();
+
+        let rewrite = |idx: usize, ez: &Zone<T>| -> Result<Vec<u8>, DiskError> {
+            let mut bv = encoding.decode(self.bitset.get(idx), len)?;
+            f(ez, &mut bv);
+
+            let mut column = vec![];
+            encoding.encode(&bv, &mut column)?;
+            Ok(column)
+        };
+
+        if self.manifest.params.container {
+            return Ok(Some(
+                self.exclusions
+                    .par_iter()
+                    .enumerate()
+                    .map(|(idx, ez)| rewrite(idx, ez))
+                    .collect::<Result<Vec<_>, DiskError>>()?,
+            ));
+        }
+
+        // Write every column to a temporary file first, so that a failure part way through leaves the existing columns untouched.
+        let columns = self
+            .exclusions
+            .par_iter()
+            .enumerate()
+            .map(|(idx, ez)| {
+                let tmp = self.path.join(format!("{}.column.tmp", idx));
+                fs::write(&tmp, rewrite(idx, ez)?)?;
+                Ok(tmp)
+            })
+            .collect::<Result<Vec<_>, DiskError>>()?;
+
+        self.bitset = Columns::Files(
+            columns
+                .into_iter()
+                .enumerate()
+                .map(|(idx, tmp)| {
+                    let path = self.path.join(format!("{}.column", idx));
+                    std::fs::rename(tmp, &path)?;
+                    unsafe { Ok(memmap2::Mmap::map(&File::open(path)?)?) }
+                })
+                .collect::<Result<Vec<_>, DiskError>>()?,
+        );
+
+        Ok(None)
+    }
+
+    /// Perform a range search for every point in `points`, with the same radius `threshold` for each query.
+    ///
+    /// Results are returned in the same order as `points`. See [`range_search_batch_indices`](Disk::range_search_batch_indices) for details.
+    pub fn range_search_batch(
+        &self,
+        points: &[T],
+        threshold: f64,
+    ) -> Result<Vec<Vec<(T, f64)>>, DiskError> {
+        let queries = points
+            .iter()
+            .map(|pt| (pt.clone(), threshold))
+            .collect::<Vec<_>>();
+
+        self.range_search_batch_thresholds(&queries)
+    }
+
+    /// Perform a range search for every `(point, threshold)` pair in `queries`.
+    ///
+    /// Results are returned in the same order as `queries`. See [`range_search_batch_indices`](Disk::range_search_batch_indices) for details.
+    pub fn range_search_batch_thresholds(
+        &self,
+        queries: &[(T, f64)],
+    ) -> Result<Vec<Vec<(T, f64)>>, DiskError> {
+        self.range_search_batch_indices(queries)?
+            .into_par_iter()
+            .map(|res| {
+                res.into_iter()
+                    .map(|(idx, d)| Ok((self.dataset.point(idx)?.into_owned(), d)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Perform a range search for every `(point, threshold)` pair in `queries`, returning the index of each point in the dataset.
+    ///
+    /// Results are returned in the same order as `queries`.
+    ///
+    /// Every column needed by at least one query in the batch is decoded exactly once, rather than once per query.
+    /// Queries are then distributed across threads.
+    pub fn range_search_batch_indices(
+        &self,
+        queries: &[(T, f64)],
+    ) -> Result<Vec<Vec<(usize, f64)>>, DiskError> {
+        match self.manifest.params.column_encoding {
+            ColumnEncoding::Bitvec => self.batch_indices::<Blocks>(queries),
+            ColumnEncoding::Roaring => self.batch_indices::<RoaringBitmap>(queries),
+        }
+    }
+
+    fn batch_indices<'a, C: Column<'a>>(
+        &'a self,
+        queries: &[(T, f64)],
+    ) -> Result<Vec<Vec<(usize, f64)>>, DiskError> {
+        let partitions = queries
+            .par_iter()
+            .map(|(point, threshold)| {
+                let mut ins = vec![];
+                let mut outs = vec![];
+
+                for (idx, ez) in self.exclusions.iter().enumerate() {
+                    if ez.must_be_in(point, *threshold) {
+                        ins.push(idx);
+                    } else if ez.must_be_out(point, *threshold) {
+                        outs.push(idx);
+                    }
+                }
+
+                (ins, outs)
+            })
+            .collect::<Vec<_>>();
+
+        let mut needed = vec![false; self.exclusions.len()];
+        for idx in partitions
+            .iter()
+            .flat_map(|(ins, outs)| ins.iter().chain(outs))
+        {
+            needed[*idx] = true;
+        }
+
+        let columns = needed
+            .into_par_iter()
+            .enumerate()
+            .map(|(idx, needed)| needed.then(|| C::decode(self.bitset.get(idx))).transpose())
+            .collect::<Result<Vec<_>, DiskError>>()?;
+
+        queries
+            .par_iter()
+            .zip(partitions)
+            .map(|((point, threshold), (ins, outs))| {
+                let ins = ins
+                    .into_iter()
+                    .map(|idx| columns[idx].as_ref().unwrap())
+                    .collect::<Vec<_>>();
+                let outs = outs
+                    .into_iter()
+                    .map(|idx| columns[idx].as_ref().unwrap())
+                    .collect::<Vec<_>>();
+
+                C::filter(
+                    &ins,
+                    &outs,
+                    &self.tombstones,
+                    &self.removed,
+                    self.block_size,
+                )
+                .into_par_iter()
+                .map(|idx| Ok((idx, point.distance(&*self.dataset.point(idx)?))))
+                .filter(|res| !matches!(res, Ok((_, d)) if d > threshold))
+                .collect::<Result<Vec<_>, DiskError>>()
+            })
+            .collect()
+    }
+
+    /// Returns the indices of points which could not be excluded from a range search.
+    fn candidates(&self, point: &T, threshold: f64) -> Result<Vec<usize>, DiskError> {
+        let (ins, outs) = self.partition(point, threshold);
+        self.filter_columns(&ins, &outs)
+    }
+
+    /// Returns the indices of zones which `point` must be in, and must be out of.
+    fn partition(&self, point: &T, threshold: f64) -> (Vec<usize>, Vec<usize>) {
+        self.exclusions
+            .par_iter()
+            .enumerate()
+            .filter_map(|(idx, ez)| {
+                if ez.must_be_in(point, threshold) {
+                    Some(Either::Left(idx))
+                } else if ez.must_be_out(point, threshold) {
+                    Some(Either::Right(idx))
+                } else {
+                    None
+                }
+            })
+            .partition_map(|x| x)
+    }
+
+    /// Returns the indices of points which are in every zone of `ins`, in no zone of `outs`, and have not been removed.
+    fn filter_columns(&self, ins: &[usize], outs: &[usize]) -> Result<Vec<usize>, DiskError> {
+        match self.manifest.params.column_encoding {
+            ColumnEncoding::Bitvec => self.filter::<Blocks>(ins, outs),
+            ColumnEncoding::Roaring => self.filter::<RoaringBitmap>(ins, outs),
+        }
+    }
+
+    /// Decodes the columns `ins` and `outs` and returns the indices of points which pass through them.
+    fn filter<'a, C: Column<'a>>(
+        &'a self,
+        ins: &[usize],
+        outs: &[usize],
+    ) -> Result<Vec<usize>, DiskError> {
+        let ins = ins
+            .par_iter()
+            .map(|idx| C::decode(self.bitset.get(*idx)))
+            .collect::<Result<Vec<_>, DiskError>>()?;
+
+        let outs = outs
+            .par_iter()
+            .map(|idx| C::decode(self.bitset.get(*idx)))
+            .collect::<Result<Vec<_>, DiskError>>()?;
+
+        Ok(C::filter(
+            &ins.iter().collect::<Vec<_>>(),
+            &outs.iter().collect::<Vec<_>>(),
+            &self.tombstones,
+            &self.removed,
+            self.block_size,
+        ))
+    }
+
+    fn make_column(
+        dataset: &[T],
+        encoding: ColumnEncoding,
+        ez: &Zone<T>,
+    ) -> Result<Vec<u8>, DiskError> {
+        let bv = dataset.iter().map(|pt| ez.is_in(pt)).collect::<BitVec>();
+
+        let mut column = vec![];
+        encoding.encode(&bv, &mut column)?;
+        Ok(column)
+    }
+}
+
+/// Storage for the points of a [`Disk`].
+enum Dataset<T> {
+    /// Points are held in memory, and written to a single file whenever they change.
+    Memory(Vec<T>),
+    /// Points are serialized to fixed-size records in a memory mapped file, and only deserialized when they are needed.
+    Mapped {
+        mmap: memmap2::Mmap,
+        stride: usize,
+        len: usize,
+        _marker: PhantomData<T>,
+    },
+}
+
+impl<T> Dataset<T>
+where
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Writes `points` to a memory mapped file in the directory `dir`.
+    fn mapped(dir: &Path, points: &[T]) -> Result<Self, DiskError> {
+        let stride = match points.first() {
+            Some(point) => bincode::serialized_size(point)? as usize,
+            None => 0,
+        };
+        check_stride(stride, points)?;
+
+        Ok(Dataset::Mapped {
+            mmap: write_records(dir, points.iter(), false)?,
+            stride,
+            len: points.len(),
+            _marker: PhantomData,
+        })
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Dataset::Memory(points) => points.len(),
+            Dataset::Mapped { len, .. } => *len,
+        }
+    }
+
+    /// Returns the points of a dataset held in memory.
+    fn memory(&self) -> Option<&[T]> {
+        match self {
+            Dataset::Memory(points) => Some(points),
+            Dataset::Mapped { .. } => None,
+        }
+    }
+
+    fn stride(&self) -> Option<usize> {
+        match self {
+            Dataset::Memory(_) => None,
+            Dataset::Mapped { stride, .. } => Some(*stride),
+        }
+    }
+
+    /// Returns the point at `index`.
+    ///
+    /// # Panics
+    /// This function will panic if `index` is out of bounds.
+    fn point(&self, index: usize) -> Result<Cow<'_, T>, DiskError> {
+        match self {
+            Dataset::Memory(points) => Ok(Cow::Borrowed(&points[index])),
+            Dataset::Mapped {
+                mmap, stride, len, ..
+            } => {
+                assert!(index < *len);
+                let record = &mmap[index * stride..(index + 1) * stride];
+                Ok(Cow::Owned(bincode::deserialize(record)?))
+            }
+        }
+    }
+
+    /// Checks that `points` can be stored, which for a memory mapped dataset means that they serialize to exactly `stride` bytes.
+    fn check(&self, points: &[T]) -> Result<(), DiskError> {
+        match self {
+            Dataset::Memory(_) => Ok(()),
+            Dataset::Mapped { stride, .. } => check_stride(*stride, points),
+        }
+    }
+
+    /// Appends `points` to the dataset, which must already have been [checked](Dataset::check).
+    fn extend(&mut self, dir: &Path, points: Vec<T>) -> Result<(), DiskError> {
+        match self {
+            Dataset::Memory(dataset) => dataset.extend(points),
+            Dataset::Mapped { mmap, len, .. } => {
+                *mmap = write_records(dir, points.iter(), true)?;
+                *len += points.len();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drops every point marked in `removed` from the dataset.
+    fn compact(&mut self, dir: &Path, removed: &BitSlice) -> Result<(), DiskError> {
+        match self {
+            Dataset::Memory(dataset) => {
+                let mut iter = removed.iter().by_vals();
+                dataset.retain(|_| !iter.next().unwrap());
+            }
+            // Records are copied byte for byte, so the remaining points are never deserialized or held in memory
+            Dataset::Mapped {
+                mmap, stride, len, ..
+            } => {
+                let compacted = write_mapped(dir, false, |file| {
+                    for idx in removed.iter_zeros() {
+                        file.write_all(&mmap[idx * *stride..(idx + 1) * *stride])?;
+                    }
+                    Ok(())
+                })?;
+                *mmap = compacted;
+                *len = removed.count_zeros();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns an error unless every point in `points` serializes to exactly `stride` bytes.
+fn check_stride<T>(stride: usize, points: &[T]) -> Result<(), DiskError>
+where
+    T: Serialize,
+{
+    for point in points {
+        let size = bincode::serialized_size(point)? as usize;
+        if size != stride {
+            return Err(DiskError::Stride {
+                found: size,
+                expected: stride,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes `points` to the memory mapped dataset in the directory `dir`, either appending them to or replacing the existing points,
+/// and maps the resulting file.
+fn write_records<'a, T>(
+    dir: &Path,
+    points: impl Iterator<Item = &'a T>,
+    append: bool,
+) -> Result<memmap2::Mmap, DiskError>
+where
+    T: Serialize + 'a,
+{
+    write_mapped(dir, append, |file| {
+        for point in points {
+            bincode::serialize_into(&mut *file, point)?;
+        }
+        Ok(())
+    })
+}
+
+/// Writes records with `write` to the memory mapped dataset in the directory `dir`, either appending them to or replacing the
+/// existing records, and maps the resulting file.
+fn write_mapped(
+    dir: &Path,
+    append: bool,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), DiskError>,
+) -> Result<memmap2::Mmap, DiskError> {
+    let path = dir.join(MAPPED_DATASET);
+    // Points are only ever appended to the end of a mapped file, so existing mappings remain valid. Replacing the points
+    // is done through a temporary file, rather than truncating a file which may still be mapped.
+    let target = match append {
+        true => path.clone(),
+        false => dir.join(format!("{}.tmp", MAPPED_DATASET)),
+    };
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(append)
+        .write(true)
+        .truncate(!append)
+        .open(&target)?;
+    let mut file = BufWriter::new(file);
+    write(&mut file)?;
+    file.flush()?;
+    drop(file);
+
+    if !append {
+        fs::rename(target, &path)?;
+    }
+
+    unsafe { Ok(memmap2::Mmap::map(&File::open(path)?)?) }
+}
+
+/// Returns the definitions of `zones` to be persisted. Custom exclusion zones cannot be persisted, so are recorded as `None`.
+fn definitions<T>(zones: &[Zone<T>]) -> Vec<Option<&Zone<T>>> {
+    zones
+        .iter()
+        .map(|ez| match ez {
+            Zone::Custom(_) => None,
+            ez => Some(ez),
+        })
+        .collect()
+}
+
+/// Writes a container holding `columns` to `path`, and maps it.
+///
+/// The container is written to a temporary file and then renamed, so a failure leaves the existing container untouched.
+fn write_container<T>(
+    path: &Path,
+    manifest: &Manifest,
+    zones: &[Option<&Zone<T>>],
+    points: &[T],
+    tombstones: &BitSlice,
+    columns: &[&[u8]],
+) -> Result<Columns, DiskError>
+where
+    T: Serialize,
+{
+    // Every column is aligned, so that its blocks can be read in place
+    let mut offset = 0;
+    let entries = columns
+        .iter()
+        .map(|column| {
+            let entry = ColumnEntry {
+                offset,
+                len: column.len() as u64,
+                checksum: crc32fast::hash(column),
+            };
+            offset = (offset + entry.len).next_multiple_of(COLUMN_ALIGN as u64);
+            entry
         })
-    }
+        .collect::<Vec<_>>();
+    let header = bincode::serialize(&(manifest, zones, points, &entries))?;
 
-    fn ball_exclusions(
-        builder: &Builder<T>,
-        ref_points: &[T],
-    ) -> Vec<Box<dyn ExclusionSync<T> + 'a>> {
-        let radii = [
-            builder.mean_distance - 2.0 * builder.radius_increment,
-            builder.mean_distance - builder.radius_increment,
-            builder.mean_distance,
-            builder.mean_distance + builder.radius_increment,
-            builder.mean_distance + 2.0 * builder.radius_increment,
-        ];
-
-        ref_points
-            .iter()
-            .cartesian_product(radii.into_iter())
-            .map(|(point, radius)| {
-                Box::new(BallExclusion::new(point.clone(), radius)) as Box<dyn ExclusionSync<T>>
-            })
-            .collect()
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut file = BufWriter::new(File::create(&tmp)?);
+    file.write_all(&CONTAINER_MAGIC)?;
+    file.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    file.write_all(&crc32fast::hash(&header).to_le_bytes())?;
+    file.write_all(&(header.len() as u64).to_le_bytes())?;
+    file.write_all(&header)?;
+    pad(&mut file, CONTAINER_PREAMBLE + header.len())?;
+    // Bits past the end of the tombstones are left unset, so a removal never has to clear them
+    let mut words = tombstones.to_bitvec();
+    words.set_uninitialized(false);
+    for word in words.as_raw_slice() {
+        file.write_all(&word.to_le_bytes())?;
     }
+    pad(&mut file, words.as_raw_slice().len() * 8)?;
+    for column in columns {
+        file.write_all(column)?;
+        pad(&mut file, column.len())?;
+    }
+    file.flush()?;
+    drop(file);
+    fs::rename(&tmp, path)?;
 
-    fn sheet_exclusions(
-        _builder: &Builder<T>,
-        ref_points: &[T],
-    ) -> Vec<Box<dyn ExclusionSync<T> + 'a>> {
-        ref_points
+    let offset = (CONTAINER_PREAMBLE + header.len()).next_multiple_of(COLUMN_ALIGN);
+    let data = (offset + words.as_raw_slice().len() * 8).next_multiple_of(COLUMN_ALIGN);
+    Ok(Columns::Container {
+        mmap: unsafe { memmap2::Mmap::map(&File::open(path)?)? },
+        ranges: entries
             .iter()
-            .combinations(2)
-            .map(|x| {
-                Box::new(SheetExclusion::new(x[0].clone(), x[1].clone(), 0.0))
-                    as Box<dyn ExclusionSync<T>>
-            })
-            .collect()
-    }
+            .map(|entry| data + entry.offset as usize..data + (entry.offset + entry.len) as usize)
+            .collect(),
+        checksums: entries.iter().map(|entry| entry.checksum).collect(),
+        tombstones: offset as u64,
+    })
+}
 
-    fn make_bitset(
-        _block_size: usize,
-        builder: &Builder<T>,
-        path: PathBuf,
-        exclusions: &[Box<dyn ExclusionSync<T> + 'a>],
-    ) -> Result<Vec<memmap2::Mmap>, DiskError> {
-        exclusions
-            .par_iter()
-            .enumerate()
-            .map(|(idx, ez)| Self::make_mmap(&builder.dataset, path.clone(), idx, ez.as_ref()))
-            .collect::<Result<Vec<_>, _>>()
-    }
+/// Pads a section of `len` bytes with zeroes, up to the next [`COLUMN_ALIGN`] boundary.
+fn pad<W: Write>(writer: &mut W, len: usize) -> Result<(), DiskError> {
+    Ok(writer.write_all(&[0; COLUMN_ALIGN][..len.next_multiple_of(COLUMN_ALIGN) - len])?)
+}
 
-    fn make_mmap(
-        dataset: &[T],
-        mut path: PathBuf,
-        index: usize,
-        ez: &(dyn ExclusionSync<T> + 'a),
-    ) -> Result<memmap2::Mmap, DiskError> {
-        let bv = dataset.iter().map(|pt| ez.is_in(pt)).collect::<BitVec>();
+/// Opens the file at `path`, returning [`DiskError::Missing`] if it does not exist.
+fn open_file(path: &Path) -> Result<File, DiskError> {
+    File::open(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => DiskError::Missing(path.to_owned()),
+        _ => DiskError::Io(e),
+    })
+}
 
-        path.push(format!("{}.bincode", index));
-        let file = File::create(&path)?;
-        bincode::serialize_into(file, &bv)?;
+/// Reads the file `name` in the directory `dir`.
+fn read<V>(dir: &Path, name: &str) -> Result<V, DiskError>
+where
+    V: DeserializeOwned,
+{
+    let file = BufReader::new(open_file(&dir.join(name))?);
+    Ok(bincode::deserialize_from(file)?)
+}
 
-        unsafe { Ok(memmap2::Mmap::map(&File::open(path)?)?) }
-    }
+/// Writes `value` to the file `name` in the directory `dir`.
+///
+/// The file is written to a temporary file and then renamed, so a failure leaves the existing file untouched.
+fn write<V>(dir: &Path, name: &str, value: &V) -> Result<(), DiskError>
+where
+    V: Serialize + ?Sized,
+{
+    let tmp = dir.join(format!("{}.tmp", name));
+    let mut file = BufWriter::new(File::create(&tmp)?);
+    bincode::serialize_into(&mut file, value)?;
+    file.flush()?;
+
+    fs::rename(tmp, dir.join(name))?;
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{metric::Euclidean, BitPart};
-    use sisap_data::{colors::parse_colors, nasa::parse_nasa, parser::parse};
+    use crate::{
+        exclusions::tests::Dimension,
+        metric::Euclidean,
+        tests::{nasa_fixture, TempPath},
+        BitPart,
+    };
+    use sisap_data::{
+        colors::parse_colors,
+        nasa::{Nasa, NASA_DIMENSION},
+        parser::parse,
+    };
     use std::fs;
 
     use super::*;
 
-    pub(crate) const NASA: &str = include_str!("../sisap-data/src/nasa.ascii");
     pub(crate) const COLORS: &str = include_str!("../sisap-data/src/colors.ascii");
 
-    fn test<T>(dataset: &Vec<T>, bitpart: &Disk<T>, query: T, threshold: f64)
+    fn test<T>(dataset: &[T], bitpart: &Disk<T>, query: T, threshold: f64)
     where
-        for<'a> T: Metric + Send + Sync + 'a,
+        for<'a> T: Metric + Send + Sync + Serialize + DeserializeOwned + 'a,
     {
         let res = bitpart.range_search(query.clone(), threshold).unwrap();
 
@@ -229,7 +1610,7 @@ mod tests {
 
         // Check results match up with linear search
         let brute_force = dataset
-            .into_iter()
+            .iter()
             .map(|pt| pt.distance(&query))
             .filter(|d| *d <= threshold)
             .count();
@@ -237,28 +1618,73 @@ mod tests {
         assert_eq!(res.len(), brute_force);
     }
 
+    fn test_knn(
+        bitpart: &Disk<Euclidean<Vec<f64>>>,
+        query: Euclidean<Vec<f64>>,
+        nn: &[(usize, f64)],
+    ) {
+        // The query point itself is always its own nearest neighbour, and is not included in `nn`.
+        let res = bitpart.knn_search(query, nn.len() + 1).unwrap();
+
+        assert_eq!(res[0].1, 0.0);
+        assert_eq!(
+            res[1..].iter().map(|(_, d)| *d).collect::<Vec<_>>(),
+            nn.iter().map(|(_, d)| *d).collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn sisap_nasa_par() {
-        std::fs::remove_dir_all("/tmp/sisap_nasa_par/").ok();
-        let nasa = parse_nasa(NASA)
-            .unwrap()
-            .into_iter()
-            .map(Euclidean::new)
-            .collect::<Vec<_>>();
+        let dir = TempPath::new("sisap_nasa_par");
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = builder.build_on_disk(&dir, Some(8192)).unwrap();
+
+        test(&nasa, &bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_stats() {
+        let dir = TempPath::new("sisap_nasa_stats");
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = builder.build_on_disk(&dir, Some(8192)).unwrap();
+
+        let (res, stats) = bitpart
+            .range_search_stats(query.clone(), threshold)
+            .unwrap();
+        assert_eq!(
+            res,
+            bitpart
+                .range_search_indices(query.clone(), threshold)
+                .unwrap()
+        );
+
+        assert_eq!(stats.zones, bitpart.zones());
+        assert!(stats.must_be_in + stats.must_be_out <= stats.zones);
+        assert_eq!(stats.results, res.len());
+        assert!(stats.candidates >= stats.results && stats.candidates < nasa.len());
+    }
+
+    #[test]
+    fn sisap_nasa_custom() {
+        let dir = TempPath::new("sisap_nasa_custom");
+        let (nasa, builder, query, threshold) = nasa_fixture();
 
-        let bitpart = Builder::new(nasa.clone(), 40)
-            .build_on_disk("/tmp/sisap_nasa_par/", Some(8192))
+        let bitpart = (0..NASA_DIMENSION)
+            .fold(builder, |builder, dim| {
+                builder.exclusion(Dimension { dim, value: 0.0 })
+            })
+            .build_on_disk(&dir, Some(8192))
             .unwrap();
-        let query = nasa[317].clone();
-        let threshold = 1.0;
+        assert_eq!(bitpart.zones(), 40 * 5 + 780 + NASA_DIMENSION);
 
         test(&nasa, &bitpart, query, threshold);
-        std::fs::remove_dir_all("/tmp/sisap_nasa_par/").unwrap();
     }
 
     #[test]
     fn sisap_colors_par() {
-        std::fs::remove_dir_all("/tmp/sisap_colors_par/").ok();
+        let dir = TempPath::new("sisap_colors_par");
         let colors = parse_colors(COLORS)
             .unwrap()
             .into_iter()
@@ -266,18 +1692,17 @@ mod tests {
             .collect::<Vec<_>>();
 
         let bitpart = Builder::new(colors.clone(), 40)
-            .build_on_disk("/tmp/sisap_colors_par/", Some(8192))
+            .build_on_disk(&dir, Some(8192))
             .unwrap();
         let query = colors[70446].clone();
         let threshold = 0.5;
 
         test(&colors, &bitpart, query, threshold);
-        std::fs::remove_dir_all("/tmp/sisap_colors_par/").unwrap();
     }
 
     #[test]
     fn nearest_neighbour() {
-        std::fs::remove_dir_all("/tmp/nn/").ok();
+        let dir = TempPath::new("nn");
 
         let points = parse(&fs::read_to_string("data/100k_d20_flat.ascii").unwrap())
             .unwrap()
@@ -293,44 +1718,707 @@ mod tests {
         let queries = points
             .iter()
             .cloned()
-            .zip(nns.into_iter())
+            .zip(nns)
             .map(|(pt, nn)| (pt, nn.last().unwrap().1))
             .take(1000)
             .collect::<Vec<_>>();
 
         let bitpart = Builder::new(points.clone(), 40)
-            .build_on_disk("/tmp/nn/", Some(8192))
+            .build_on_disk(&dir, Some(8192))
             .unwrap();
 
         for (query, threshold) in queries {
             test(&points, &bitpart, query, threshold);
         }
+    }
+
+    #[test]
+    #[should_panic]
+    fn build_on_disk_zero_block_size() {
+        let points = (0..100)
+            .map(|x| Euclidean::new([x as f64]))
+            .collect::<Vec<_>>();
+        let dir = TempPath::new("zero_block_size");
+        let _ = Builder::new(points, 4).build_on_disk(&dir, Some(0));
+    }
+
+    #[test]
+    fn sisap_nasa_extend() {
+        let dir = TempPath::new("sisap_nasa_extend");
+        let (nasa, _, _, threshold) = nasa_fixture();
+
+        let (first, rest) = nasa.split_at(nasa.len() / 2);
+        let mut bitpart = Builder::new(first.to_vec(), 40)
+            .build_on_disk(&dir, Some(512))
+            .unwrap();
+        bitpart.insert(rest[0].clone()).unwrap();
+        bitpart.extend(rest[1..].iter().cloned()).unwrap();
+        assert_eq!(bitpart.len(), nasa.len());
+        assert_eq!(
+            bitpart
+                .get(nasa.len() - 1)
+                .unwrap()
+                .unwrap()
+                .distance(&nasa[nasa.len() - 1]),
+            0.0
+        );
+
+        let query = nasa[nasa.len() - 317].clone();
+        test(&nasa, &bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_remove() {
+        let dir = TempPath::new("sisap_nasa_remove");
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        // Remove every other point
+        let mut bitpart = builder.build_on_disk(&dir, Some(512)).unwrap();
+        for idx in (0..nasa.len()).step_by(2) {
+            assert!(bitpart.remove(idx).unwrap());
+        }
+        assert!(!bitpart.remove(0).unwrap());
+        assert!(!bitpart.remove(nasa.len()).unwrap());
+        assert!(bitpart.get(0).unwrap().is_none());
+        assert_eq!(bitpart.len(), nasa.len() / 2);
+
+        let remaining = nasa.iter().skip(1).step_by(2).cloned().collect::<Vec<_>>();
+        let brute_force = remaining
+            .iter()
+            .filter(|pt| pt.distance(&query) <= threshold)
+            .count();
+
+        let res = bitpart
+            .range_search_indices(query.clone(), threshold)
+            .unwrap();
+        assert!(res.iter().all(|(idx, _)| idx % 2 == 1));
+        assert_eq!(res.len(), brute_force);
+
+        let batch = bitpart
+            .range_search_batch_indices(&[(query.clone(), threshold)])
+            .unwrap();
+        assert_eq!(batch[0], res);
+
+        let res = bitpart.knn_search_indices(query.clone(), 10).unwrap();
+        assert_eq!(res.len(), 10);
+        assert!(res.iter().all(|(idx, _)| idx % 2 == 1));
+
+        bitpart.compact().unwrap();
+        assert_eq!(bitpart.len(), remaining.len());
+        assert_eq!(
+            bitpart.get(0).unwrap().unwrap().distance(&remaining[0]),
+            0.0
+        );
+
+        test(&remaining, &bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_cull() {
+        let dir = TempPath::new("sisap_nasa_cull");
+        let built = TempPath::new("sisap_nasa_cull_build");
+        let container = TempPath::new("sisap_nasa_cull_container");
+        let (nasa, builder, query, threshold) = nasa_fixture();
+        let builder = builder.ref_points(20);
+        let columns = |path: &Path| {
+            fs::read_dir(path)
+                .unwrap()
+                .filter(|entry| {
+                    entry.as_ref().unwrap().path().extension() == Some("column".as_ref())
+                })
+                .count()
+        };
+
+        let mut bitpart = builder.clone().build_on_disk(&dir, Some(512)).unwrap();
+        bitpart.cull_by_popcnt(0.95).unwrap();
+        let popcnt = bitpart.zones();
+        assert!(popcnt < 20 * 5 + 190);
+        bitpart.cull_by_similarity(0.95).unwrap();
+        let zones = bitpart.zones();
+        assert!(zones <= popcnt);
+        assert_eq!(columns(&dir), zones);
+        test(&nasa, &bitpart, query.clone(), threshold);
+        drop(bitpart);
+
+        let bitpart = Disk::<Euclidean<Nasa>>::open(&dir).unwrap();
+        assert_eq!(bitpart.zones(), zones);
+        test(&nasa, &bitpart, query.clone(), threshold);
+
+        // Culling while building never writes the culled columns
+        let bitpart = builder
+            .clone()
+            .cull_by_popcnt(0.95)
+            .cull_by_similarity(0.95)
+            .build_on_disk(&built, Some(512))
+            .unwrap();
+        assert_eq!(bitpart.zones(), zones);
+        assert_eq!(columns(&built), zones);
+        test(&nasa, &bitpart, query.clone(), threshold);
+
+        let mut bitpart = builder
+            .clone()
+            .container(true)
+            .build_on_disk(&container, Some(512))
+            .unwrap();
+        bitpart.cull_by_popcnt(0.95).unwrap();
+        assert_eq!(bitpart.zones(), popcnt);
+        drop(bitpart);
+
+        let bitpart = Disk::<Euclidean<Nasa>>::open(&container).unwrap();
+        assert_eq!(bitpart.zones(), popcnt);
+        test(&nasa, &bitpart, query.clone(), threshold);
+
+        // Compressed columns are decoded before they are compared
+        fs::remove_dir_all(&dir).unwrap();
+        let mut bitpart = builder
+            .column_encoding(ColumnEncoding::Roaring)
+            .build_on_disk(&dir, Some(512))
+            .unwrap();
+        bitpart.cull_to_independent(50).unwrap();
+        assert_eq!(bitpart.zones(), 50);
+        assert_eq!(columns(&dir), 50);
+        test(&nasa, &bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_open() {
+        let dir = TempPath::new("sisap_nasa_open");
+        let (nasa, _, query, threshold) = nasa_fixture();
+
+        let (first, rest) = nasa.split_at(nasa.len() / 2);
+        let mut bitpart = Builder::new(first.to_vec(), 40)
+            .build_on_disk(&dir, Some(512))
+            .unwrap();
+        bitpart.extend(rest.iter().cloned()).unwrap();
+        bitpart.remove(0).unwrap();
+        let expected = bitpart
+            .range_search_indices(query.clone(), threshold)
+            .unwrap();
+        drop(bitpart);
+
+        let bitpart = Disk::<Euclidean<Nasa>>::open(&dir).unwrap();
+        assert_eq!(bitpart.len(), nasa.len() - 1);
+        assert_eq!(bitpart.zones(), 40 * 5 + 780);
+        assert!(bitpart.get(0).unwrap().is_none());
+        assert_eq!(
+            bitpart
+                .range_search_indices(query.clone(), threshold)
+                .unwrap(),
+            expected
+        );
+
+        test(&nasa[1..], &bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_roaring() {
+        let dir = TempPath::new("sisap_nasa_roaring");
+        let (nasa, _, query, threshold) = nasa_fixture();
+
+        let (first, rest) = nasa.split_at(nasa.len() / 2);
+        let mut bitpart = Builder::new(first.to_vec(), 40)
+            .column_encoding(ColumnEncoding::Roaring)
+            .build_on_disk(&dir, Some(512))
+            .unwrap();
+        bitpart.extend(rest.iter().cloned()).unwrap();
+        test(&nasa, &bitpart, query.clone(), threshold);
+
+        // Remove every other point
+        for idx in (0..nasa.len()).step_by(2) {
+            assert!(bitpart.remove(idx).unwrap());
+        }
+        let remaining = nasa.iter().skip(1).step_by(2).cloned().collect::<Vec<_>>();
+
+        let res = bitpart
+            .range_search_indices(query.clone(), threshold)
+            .unwrap();
+        assert!(res.iter().all(|(idx, _)| idx % 2 == 1));
+        assert_eq!(
+            res.len(),
+            remaining
+                .iter()
+                .filter(|pt| pt.distance(&query) <= threshold)
+                .count()
+        );
+
+        let batch = bitpart
+            .range_search_batch_indices(&[(query.clone(), threshold)])
+            .unwrap();
+        assert_eq!(batch[0], res);
+
+        // Removals survive reopening before compaction
+        drop(bitpart);
+        let mut bitpart = Disk::<Euclidean<Nasa>>::open(&dir).unwrap();
+        assert_eq!(
+            bitpart
+                .range_search_indices(query.clone(), threshold)
+                .unwrap(),
+            res
+        );
+
+        bitpart.compact().unwrap();
+        drop(bitpart);
+
+        let bitpart = Disk::<Euclidean<Nasa>>::open(&dir).unwrap();
+        assert_eq!(bitpart.len(), remaining.len());
+        test(&remaining, &bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_container() {
+        let path = TempPath::new("sisap_nasa_container");
+        let (nasa, _, query, threshold) = nasa_fixture();
+
+        let (first, rest) = nasa.split_at(nasa.len() / 2);
+        let mut bitpart = Builder::new(first.to_vec(), 40)
+            .container(true)
+            .build_on_disk(&path, Some(512))
+            .unwrap();
+        assert!(path.is_file());
+        bitpart.extend(rest.iter().cloned()).unwrap();
+        test(&nasa, &bitpart, query.clone(), threshold);
+
+        // Removals only update the tombstones, leaving the columns in place
+        let len = fs::metadata(&path).unwrap().len();
+        for idx in (0..nasa.len()).step_by(50) {
+            assert!(bitpart.remove(idx).unwrap());
+        }
+        assert!(!bitpart.remove(0).unwrap());
+        assert!(bitpart.verify().is_ok());
+        assert_eq!(fs::metadata(&path).unwrap().len(), len);
+        let remaining = nasa
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| idx % 50 != 0)
+            .map(|(_, pt)| pt.clone())
+            .collect::<Vec<_>>();
+        drop(bitpart);
+
+        // Removals survive reopening before compaction
+        let mut bitpart = Disk::<Euclidean<Nasa>>::open(&path).unwrap();
+        assert_eq!(bitpart.len(), remaining.len());
+        let res = bitpart
+            .range_search_indices(query.clone(), threshold)
+            .unwrap();
+        assert!(res.iter().all(|(idx, _)| idx % 50 != 0));
+
+        bitpart.compact().unwrap();
+        drop(bitpart);
+
+        let bitpart = Disk::<Euclidean<Nasa>>::open(&path).unwrap();
+        assert_eq!(bitpart.len(), remaining.len());
+        test(&remaining, &bitpart, query, threshold);
+
+        // The container is left in place
+        assert!(matches!(
+            Builder::new(remaining, 40)
+                .container(true)
+                .build_on_disk(&path, Some(512)),
+            Err(DiskError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn container_errors() {
+        let path = TempPath::new("container_errors");
+        let points = (0..100)
+            .map(|x| Euclidean::new([x as f64]))
+            .collect::<Vec<_>>();
+
+        assert!(matches!(
+            Builder::new(points.clone(), 4)
+                .container(true)
+                .map_dataset(true)
+                .build_on_disk(&path, Some(16)),
+            Err(DiskError::MappedContainer)
+        ));
+
+        let bitpart = Builder::new(points, 4)
+            .container(true)
+            .build_on_disk(&path, Some(16))
+            .unwrap();
+        assert!(bitpart.verify().is_ok());
+        let Columns::Container {
+            ranges, tombstones, ..
+        } = &bitpart.bitset
+        else {
+            panic!("expected a container");
+        };
+        let column = ranges[0].start;
+        let tombstones = *tombstones as usize;
+        drop(bitpart);
+
+        let container = fs::read(&path).unwrap();
+        let open = |bytes: &[u8]| {
+            fs::write(&path, bytes).unwrap();
+            Disk::<Euclidean<[f64; 1]>>::open(&path)
+        };
+        assert!(open(&container).is_ok());
+
+        // A flipped bit in a column, or in the header
+        for idx in [column, CONTAINER_PREAMBLE] {
+            let mut corrupt = container.clone();
+            corrupt[idx] ^= 1;
+            assert!(matches!(open(&corrupt), Err(DiskError::Corrupt)));
+        }
+
+        // A tombstone past the end of the dataset
+        let mut stray = container.clone();
+        stray[tombstones + 15] = 0x80;
+        assert!(matches!(open(&stray), Err(DiskError::Inconsistent)));
+
+        assert!(matches!(
+            open(&container[..container.len() / 2]),
+            Err(DiskError::Truncated)
+        ));
+        assert!(matches!(open(&container[..8]), Err(DiskError::Truncated)));
+        assert!(matches!(
+            open(&[0; CONTAINER_PREAMBLE]),
+            Err(DiskError::Corrupt)
+        ));
+
+        // Container written by a future version
+        let mut future = container.clone();
+        future[8..12].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(open(&future), Err(DiskError::Version { .. })));
+    }
+
+    #[test]
+    fn sisap_nasa_mapped() {
+        let dir = TempPath::new("sisap_nasa_mapped");
+        let (nasa, _, query, threshold) = nasa_fixture();
+
+        let (first, rest) = nasa.split_at(nasa.len() / 2);
+        let mut bitpart = Builder::new(first.to_vec(), 40)
+            .map_dataset(true)
+            .build_on_disk(&dir, Some(512))
+            .unwrap();
+        assert!(matches!(bitpart.dataset, Dataset::Mapped { .. }));
+        bitpart.extend(rest.iter().cloned()).unwrap();
+        test(&nasa, &bitpart, query.clone(), threshold);
+
+        for idx in (0..nasa.len()).step_by(2) {
+            bitpart.remove(idx).unwrap();
+        }
+        bitpart.compact().unwrap();
+        drop(bitpart);
+
+        let remaining = nasa.iter().skip(1).step_by(2).cloned().collect::<Vec<_>>();
+        let bitpart = Disk::<Euclidean<Nasa>>::open(&dir).unwrap();
+        assert!(matches!(bitpart.dataset, Dataset::Mapped { .. }));
+        assert_eq!(bitpart.len(), remaining.len());
+        assert_eq!(
+            bitpart.get(0).unwrap().unwrap().distance(&remaining[0]),
+            0.0
+        );
+
+        test(&remaining, &bitpart, query, threshold);
+    }
+
+    #[test]
+    fn mapped_stride() {
+        let dir = TempPath::new("mapped_stride");
+        let points = (0..100)
+            .map(|x| Euclidean::new(vec![x as f64, 0.0]))
+            .collect::<Vec<_>>();
+
+        let mut bitpart = Builder::new(points.clone(), 4)
+            .map_dataset(true)
+            .build_on_disk(&dir, Some(16))
+            .unwrap();
+
+        // Points of a different dimension serialize to a different number of bytes
+        assert!(matches!(
+            bitpart.insert(Euclidean::new(vec![0.0])),
+            Err(DiskError::Stride { .. })
+        ));
+        assert_eq!(bitpart.len(), points.len());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut points = points;
+        points.push(Euclidean::new(vec![0.0]));
+        assert!(matches!(
+            Builder::new(points, 4)
+                .map_dataset(true)
+                .try_build_on_disk(&dir, Some(16)),
+            Err(BuildError::Disk(DiskError::Stride { .. }))
+        ));
+    }
+
+    #[test]
+    fn column_layout() {
+        let bits = (0..100).map(|idx| idx % 3 == 0).collect::<BitVec>();
+
+        let mut bytes = vec![];
+        Blocks::encode(&bits, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), COLUMN_HEADER + 4 * 8);
+        assert_eq!(bytes[..4], COLUMN_MAGIC);
+
+        // Blocks are read in place, so must be aligned
+        let blocks = bytes[COLUMN_HEADER..]
+            .chunks(32)
+            .map(|block| {
+                u64x4::new(std::array::from_fn(|idx| {
+                    u64::from_ne_bytes(block[idx * 8..][..8].try_into().unwrap())
+                }))
+            })
+            .collect::<Vec<_>>();
+        let mut aligned = vec![u64x4::ZERO; blocks.len() + 1];
+        aligned[1..].copy_from_slice(&blocks);
+        let aligned =
+            unsafe { std::slice::from_raw_parts_mut(aligned.as_mut_ptr() as *mut u8, bytes.len()) };
+        aligned[..COLUMN_HEADER].copy_from_slice(&bytes[..COLUMN_HEADER]);
+
+        let column = Blocks::decode(aligned).unwrap();
+        assert_eq!(column.bits(), bits.as_bitslice());
+
+        // The same blocks as a SIMD bitvector
+        let words = column.bits().to_bitvec().into_vec();
+        let simd = bitvec_simd::BitVec::from_slice_copy(&words, bits.len());
+        assert_eq!(simd.into_bools(), bits.iter().by_vals().collect::<Vec<_>>());
+
+        assert!(matches!(
+            Blocks::decode(&aligned[..aligned.len() - 32]),
+            Err(DiskError::Inconsistent)
+        ));
+    }
+
+    #[test]
+    fn open_errors() {
+        let dir = TempPath::new("open_errors");
+        let points = (0..100)
+            .map(|x| Euclidean::new([x as f64]))
+            .collect::<Vec<_>>();
+
+        assert!(matches!(
+            Disk::<Euclidean<[f64; 1]>>::open(&dir),
+            Err(DiskError::Missing(_))
+        ));
+
+        // Custom exclusion zones cannot be persisted
+        Builder::new(points.clone(), 4)
+            .exclusion(Dimension {
+                dim: 0,
+                value: 50.0,
+            })
+            .build_on_disk(&dir, Some(16))
+            .unwrap();
+        assert!(matches!(
+            Disk::<Euclidean<[f64; 1]>>::open(&dir),
+            Err(DiskError::CustomZones)
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+
+        Builder::new(points, 4)
+            .build_on_disk(&dir, Some(16))
+            .unwrap();
+        assert!(Disk::<Euclidean<[f64; 1]>>::open(&dir).is_ok());
+
+        // A truncated column is detected when the directory is opened
+        let column = fs::read(dir.join("0.column")).unwrap();
+        fs::write(dir.join("0.column"), &column[..column.len() - 1]).unwrap();
+        assert!(matches!(
+            Disk::<Euclidean<[f64; 1]>>::open(&dir),
+            Err(DiskError::Inconsistent)
+        ));
+
+        // As is a column written by an incompatible version
+        let mut incompatible = column.clone();
+        incompatible[4..8].copy_from_slice(&2u32.to_ne_bytes());
+        fs::write(dir.join("0.column"), &incompatible).unwrap();
+        assert!(matches!(
+            Disk::<Euclidean<[f64; 1]>>::open(&dir),
+            Err(DiskError::Inconsistent)
+        ));
+
+        fs::remove_file(dir.join("0.column")).unwrap();
+        assert!(matches!(
+            Disk::<Euclidean<[f64; 1]>>::open(&dir),
+            Err(DiskError::Missing(_))
+        ));
+
+        // Directory written by a future version
+        write(&dir, MANIFEST, &(FORMAT_VERSION + 1)).unwrap();
+        assert!(matches!(
+            Disk::<Euclidean<[f64; 1]>>::open(&dir),
+            Err(DiskError::Version { .. })
+        ));
+    }
+
+    #[test]
+    fn try_build_on_disk_errors() {
+        let points = (0..100)
+            .map(|x| Euclidean::new([x as f64]))
+            .collect::<Vec<_>>();
+        let builder = Builder::new(points, 4);
+        let dir = TempPath::new("try_build");
+
+        assert!(matches!(
+            builder.clone().try_build_on_disk(&dir, Some(0)),
+            Err(BuildError::ZeroBlockSize)
+        ));
+
+        assert!(builder.clone().try_build_on_disk(&dir, Some(16)).is_ok());
+        assert!(matches!(
+            builder.try_build_on_disk(&dir, Some(16)),
+            Err(BuildError::DirectoryExists(_))
+        ));
+    }
+
+    #[test]
+    fn nearest_neighbour_batch() {
+        let dir = TempPath::new("nn_batch");
+
+        let points = parse(&fs::read_to_string("data/100k_d20_flat.ascii").unwrap())
+            .unwrap()
+            .1
+             .1
+            .into_iter()
+            .map(Euclidean::new)
+            .collect::<Vec<_>>();
+
+        let nns: Vec<Vec<(usize, f64)>> =
+            serde_json::from_str(&fs::read_to_string("data/100k_d20_flat.json").unwrap()).unwrap();
+
+        let queries = points
+            .iter()
+            .cloned()
+            .zip(nns.iter())
+            .map(|(pt, nn)| (pt, nn.last().unwrap().1))
+            .take(1000)
+            .collect::<Vec<_>>();
+
+        let bitpart = Builder::new(points.clone(), 40)
+            .build_on_disk(&dir, Some(8192))
+            .unwrap();
+
+        let res = bitpart.range_search_batch_indices(&queries).unwrap();
+        assert_eq!(res.len(), queries.len());
+
+        // Each query finds itself, followed by its nearest neighbours
+        for (mut res, nn) in res.into_iter().zip(nns) {
+            res.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+            assert_eq!(
+                res.into_iter().map(|(_, d)| d).collect::<Vec<_>>(),
+                [0.0]
+                    .into_iter()
+                    .chain(nn.into_iter().map(|(_, d)| d))
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn nearest_neighbour_knn() {
+        let dir = TempPath::new("nn_knn");
+
+        let points = parse(&fs::read_to_string("data/100k_d20_flat.ascii").unwrap())
+            .unwrap()
+            .1
+             .1
+            .into_iter()
+            .map(Euclidean::new)
+            .collect::<Vec<_>>();
+
+        let nns: Vec<Vec<(usize, f64)>> =
+            serde_json::from_str(&fs::read_to_string("data/100k_d20_flat.json").unwrap()).unwrap();
+
+        let bitpart = Builder::new(points.clone(), 40)
+            .build_on_disk(&dir, Some(8192))
+            .unwrap();
 
-        std::fs::remove_dir_all("/tmp/nn/").unwrap();
+        for (query, nn) in points.iter().cloned().zip(nns.iter()).take(1000) {
+            test_knn(&bitpart, query, nn);
+        }
     }
 }
 
 impl<T> Builder<T>
 where
-    for<'a> T: Metric + Send + Sync + 'a,
+    for<'a> T: Metric + Send + Sync + Serialize + DeserializeOwned + 'a,
 {
+    /// Set whether [`Disk`](crate::Disk) stores the dataset in a memory mapped file, rather than holding it in memory.
+    /// The default value is `false`.
+    ///
+    /// Each point is serialized to a fixed-size record, so every point must serialize to the same number of bytes. This holds for
+    /// arrays of `f64`, and for `Vec<f64>` if every point has the same dimension. Points are only deserialized when they survive
+    /// filtering by exclusion zones, or are returned from a query.
+    pub fn map_dataset(mut self, map_dataset: bool) -> Self {
+        self.map_dataset = map_dataset;
+        self
+    }
+
+    /// Set whether [`Disk`](crate::Disk) stores the whole index in a single container file at `path`, rather than a directory of files.
+    /// The default value is `false`.
+    ///
+    /// A container holds a header (a magic number, the format version, the builder parameters, the exclusion zones, the dataset and a
+    /// table of column offsets and checksums) followed by the column data, and is memory mapped as one region. It is
+    /// [verified](crate::Disk::verify) when it is [opened](crate::Disk::open). The header is followed by the tombstones of
+    /// [removed](crate::Disk::remove) points, which are updated in place and not covered by a checksum, and then by the column data.
+    /// Every other change to the index, including [compaction](crate::Disk::compact), rewrites the whole container.
+    ///
+    /// The dataset is held in memory, so a container cannot be combined with [`map_dataset`](crate::Builder::map_dataset).
+    pub fn container(mut self, container: bool) -> Self {
+        self.container = container;
+        self
+    }
+
+    /// Set how [`Disk`](crate::Disk) encodes each bitset column. The default value is [`ColumnEncoding::Bitvec`].
+    pub fn column_encoding(mut self, column_encoding: ColumnEncoding) -> Self {
+        self.column_encoding = column_encoding;
+        self
+    }
+
     /// Construct a [`Disk`](crate::Disk).
     ///
     /// `path` should be a path for the directory in which partitioning data will be stored.
     /// This function uses [`create_dir`](std::fs::create_dir) to create the directory, *not* [`create_dir_all`](std::fs::create_dir_all).
+    /// If [`container`](crate::Builder::container) is set, `path` is instead the path of the container file, whose parent directory must exist.
+    ///
+    /// # Errors
+    /// This function will return [`DiskError::Io`] if the `create_dir` call is unsuccessful, or the container already exists.
     ///
     /// # Panics
-    /// This function will panic if the `create_dir` call is unsuccessful.
-    pub fn build_on_disk<'a, P>(
+    /// This function will panic if the builder's parameters are invalid, or if `block_size` is set to `Some(0)`.
+    /// See [`try_build_on_disk`](crate::Builder::try_build_on_disk) for a non-panicking alternative.
+    pub fn build_on_disk<P>(self, path: P, block_size: Option<usize>) -> Result<Disk<T>, DiskError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        if block_size == Some(0) {
+            panic!("{}", BuildError::ZeroBlockSize);
+        }
+        self.validate().unwrap_or_else(|e| panic!("{e}"));
+        if !self.container {
+            std::fs::create_dir(&path)?;
+        }
+        Disk::setup(self, path, block_size)
+    }
+
+    /// Construct a [`Disk`](crate::Disk), returning an error if the builder's parameters are invalid,
+    /// `block_size` is set to `Some(0)`, or `path` already exists.
+    ///
+    /// See [`build_on_disk`](crate::builder::Builder::build_on_disk) for details.
+    pub fn try_build_on_disk<P>(
         self,
         path: P,
         block_size: Option<usize>,
-    ) -> Result<Disk<'a, T>, DiskError>
+    ) -> Result<Disk<T>, BuildError>
     where
-        P: AsRef<std::path::Path> + 'a,
+        P: AsRef<std::path::Path>,
     {
-        std::fs::create_dir(&path).unwrap();
-        Disk::setup(self, path, block_size)
+        if block_size == Some(0) {
+            return Err(BuildError::ZeroBlockSize);
+        }
+        if path.as_ref().exists() {
+            return Err(BuildError::DirectoryExists(path.as_ref().to_path_buf()));
+        }
+        self.validate()?;
+
+        if !self.container {
+            std::fs::create_dir(&path)?;
+        }
+        Ok(Disk::setup(self, path, block_size)?)
     }
 }
 
@@ -341,7 +2429,45 @@ pub enum DiskError {
     /// or a bitvector's file could not be accessed.
     #[error("io error")]
     Io(#[from] std::io::Error),
-    /// Could not (de)serialize a bitvector.
-    #[error("could not (de)serialize bitvector")]
+    /// Could not (de)serialize index data: a column, the manifest, the exclusion zones or the dataset.
+    #[error("could not (de)serialize index data")]
     Serde(#[from] bincode::Error),
+    /// A file needed to [open](Disk::open) a `Disk` is missing.
+    #[error("missing file {0}")]
+    Missing(PathBuf),
+    /// The directory was written by an incompatible version of this library.
+    #[error("unsupported format version {found}, expected {expected}")]
+    Version {
+        /// The version of the directory.
+        found: u32,
+        /// The version supported by this library.
+        expected: u32,
+    },
+    /// The `Disk` uses custom exclusion zones, which cannot be persisted, so cannot be [opened](Disk::open).
+    #[error("custom exclusion zones cannot be persisted")]
+    CustomZones,
+    /// The files in the directory do not match one another.
+    #[error("files are inconsistent with the manifest")]
+    Inconsistent,
+    /// A [container](crate::Builder::container) is shorter than its header describes.
+    #[error("container is truncated")]
+    Truncated,
+    /// A [container](crate::Builder::container) is not a container, or its header or a column does not match its checksum.
+    #[error("container is corrupt")]
+    Corrupt,
+    /// A [container](crate::Builder::container) cannot hold a [memory mapped](crate::Builder::map_dataset) dataset.
+    #[error("a container cannot hold a memory mapped dataset")]
+    MappedContainer,
+    /// The dataset has more points than a column with the chosen [`ColumnEncoding`] can hold.
+    #[error("a column cannot hold {0} points")]
+    Capacity(usize),
+    /// A point cannot be stored in a [memory mapped](crate::Builder::map_dataset) dataset, because it does not serialize to the same
+    /// number of bytes as the other points.
+    #[error("point serializes to {found} bytes, expected {expected}")]
+    Stride {
+        /// The size of the point.
+        found: usize,
+        /// The size of every point in the dataset.
+        expected: usize,
+    },
 }
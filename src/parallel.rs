@@ -1,12 +1,15 @@
-use crate::builder::Builder;
-use crate::exclusions::{BallExclusion, ExclusionSync, SheetExclusion};
+use crate::builder::{BuildError, Builder};
+use crate::cull;
+use crate::exclusions::{Exclusion, Zone};
+use crate::knn;
 use crate::metric::Metric;
-use crate::BitPart;
+use crate::{BitPart, QueryStats, RangeStats};
 
 use bitvec_simd::BitVec;
-use itertools::{Either, Itertools};
+use itertools::Either;
 use rayon::prelude::*;
 use std::collections::HashSet;
+use std::time::Instant;
 use thiserror::Error;
 
 /// Parallel BitPart.
@@ -24,78 +27,106 @@ use thiserror::Error;
 ///
 /// Explicit SIMD optimisations for bitvector operations are provided by [`bitvec_simd`](bitvec_simd).
 ///
+/// With the `serde` feature enabled, the index (dataset, exclusion zones and bitset columns) can be serialized and
+/// deserialized. Serialization fails if the index was built with custom exclusion zones.
+///
 /// See [`build_parallel`](crate::builder::Builder::build_parallel) for configuration.
-pub struct Parallel<'a, T> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Parallel<T> {
     dataset: Vec<T>,
-    exclusions: Vec<Box<dyn ExclusionSync<T> + 'a>>,
+    exclusions: Vec<Zone<T>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::columns"))]
     bitset: Vec<Vec<BitVec>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::columns"))]
+    tombstones: Vec<BitVec>,
     block_size: usize,
 }
 
-impl<T> BitPart<T> for Parallel<'_, T>
+impl<T> BitPart<T> for Parallel<T>
 where
     T: Metric + Send + Sync,
 {
     type Error = ParallelError;
 
     fn range_search(&self, point: T, threshold: f64) -> Result<Vec<(T, f64)>, ParallelError> {
-        if self.exclusions.is_empty() {
-            return Err(ParallelError::NoZones);
-        }
+        let res = self
+            .range_search_indices(point, threshold)?
+            .into_par_iter()
+            .map(|(idx, d)| (self.dataset.get(idx).unwrap().clone(), d))
+            .collect::<Vec<_>>();
 
-        let (ins, outs): (Vec<usize>, Vec<usize>) = self
-            .exclusions
-            .par_iter()
-            .enumerate()
-            .filter_map(|(idx, ez)| {
-                if ez.must_be_in(&point, threshold) {
-                    Some(Either::Left(idx))
-                } else if ez.must_be_out(&point, threshold) {
-                    Some(Either::Right(idx))
-                } else {
-                    None
-                }
-            })
-            .partition_map(|x| x);
+        Ok(res)
+    }
 
+    fn range_search_indices(
+        &self,
+        point: T,
+        threshold: f64,
+    ) -> Result<Vec<(usize, f64)>, ParallelError> {
         let res = self
-            .bitset
-            .par_iter()
-            .enumerate()
-            .flat_map(|(block_idx, bitvecs)| {
-                // SAFETY: Queries can only be made if there are still exclusion zones left.
-                let len = bitvecs[0].len();
+            .candidates(&point, threshold)?
+            .into_par_iter()
+            .map(|idx| (idx, point.distance(self.dataset.get(idx).unwrap())))
+            .filter(|(_, d)| *d <= threshold)
+            .collect::<Vec<_>>();
 
-                let ands = ins
-                    .iter()
-                    .map(|idx| bitvecs.get(*idx).unwrap())
-                    .fold(BitVec::ones(len), |acc, v| acc & v); // TODO: fold or reduce?
+        Ok(res)
+    }
 
-                let nots = !outs
-                    .iter()
-                    .map(|idx| bitvecs.get(*idx).unwrap())
-                    .fold(BitVec::zeros(len), |acc, v| acc | v);
+    fn range_search_stats(&self, point: T, threshold: f64) -> Result<RangeStats, ParallelError> {
+        let start = Instant::now();
+        let (ins, outs) = self.partition(&point, threshold)?;
+        let mut stats = QueryStats::new(&self.exclusions, &ins, &outs, start.elapsed());
 
-                let res = ands & nots;
+        let start = Instant::now();
+        let candidates = self.filter(&ins, &outs);
+        stats.filter_time = start.elapsed();
+        stats.candidates = candidates.len();
 
-                res.into_usizes()
-                    .into_iter()
-                    .map(|internal_idx| {
-                        self.dataset
-                            .get((block_idx * self.block_size) + internal_idx)
-                            .unwrap()
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .map(|pt| (pt.clone(), point.distance(pt)))
+        let start = Instant::now();
+        let res = candidates
+            .into_par_iter()
+            .map(|idx| (idx, point.distance(self.dataset.get(idx).unwrap())))
             .filter(|(_, d)| *d <= threshold)
             .collect::<Vec<_>>();
+        stats.distance_time = start.elapsed();
+        stats.results = res.len();
+
+        Ok((res, stats))
+    }
+
+    fn knn_search(&self, point: T, k: usize) -> Result<Vec<(T, f64)>, ParallelError> {
+        let res = self
+            .knn_search_indices(point, k)?
+            .into_par_iter()
+            .map(|(idx, d)| (self.dataset.get(idx).unwrap().clone(), d))
+            .collect::<Vec<_>>();
 
         Ok(res)
     }
 
+    fn knn_search_indices(&self, point: T, k: usize) -> Result<Vec<(usize, f64)>, ParallelError> {
+        knn::search(
+            k,
+            self.dataset.len(),
+            |idx| self.is_removed(idx),
+            |radius| self.candidates(&point, radius),
+            |indices| {
+                Ok(indices
+                    .par_iter()
+                    .map(|&idx| point.distance(self.dataset.get(idx).unwrap()))
+                    .collect())
+            },
+        )
+    }
+
     fn len(&self) -> usize {
         self.dataset.len()
+            - self
+                .tombstones
+                .iter()
+                .map(|bv| bv.count_ones())
+                .sum::<usize>()
     }
 
     fn zones(&self) -> usize {
@@ -103,69 +134,317 @@ where
     }
 
     fn is_empty(&self) -> bool {
-        self.dataset.len() == 0
+        self.len() == 0
     }
 }
 
-impl<'a, T> Parallel<'a, T>
+impl<T> Parallel<T>
 where
     T: Metric + Send + Sync,
-    dyn ExclusionSync<T>: 'a,
 {
     pub(crate) fn setup(builder: Builder<T>, block_size: Option<usize>) -> Self {
         let block_size = block_size.unwrap_or(builder.dataset.len());
-        // TODO: actually randomise this
-        let ref_points = &builder.dataset[0..(builder.ref_points as usize)];
-        let mut exclusions = Self::ball_exclusions(&builder, ref_points);
-        exclusions.extend(Self::sheet_exclusions(&builder, ref_points));
+        let exclusions = builder.zones();
         let bitset = Self::make_bitset(block_size, &builder, &exclusions);
+        let tombstones = builder
+            .dataset
+            .chunks(block_size)
+            .map(|points| BitVec::zeros(points.len()))
+            .collect();
         Self {
             dataset: builder.dataset,
             bitset,
+            tombstones,
             exclusions,
             block_size,
         }
     }
 
-    fn ball_exclusions(
-        builder: &Builder<T>,
-        ref_points: &[T],
-    ) -> Vec<Box<dyn ExclusionSync<T> + 'a>> {
-        let radii = [
-            builder.mean_distance - 2.0 * builder.radius_increment,
-            builder.mean_distance - builder.radius_increment,
-            builder.mean_distance,
-            builder.mean_distance + builder.radius_increment,
-            builder.mean_distance + 2.0 * builder.radius_increment,
-        ];
-
-        ref_points
-            .iter()
-            .cartesian_product(radii.into_iter())
-            .map(|(point, radius)| {
-                Box::new(BallExclusion::new(point.clone(), radius)) as Box<dyn ExclusionSync<T>>
+    /// Returns a reference to the point at `index` in the dataset, or `None` if it is out of bounds or has been [removed](Parallel::remove).
+    ///
+    /// Indices are those returned by [`range_search_indices`](crate::BitPart::range_search_indices) and
+    /// [`knn_search_indices`](crate::BitPart::knn_search_indices), and match the order in which points were given to the [`Builder`].
+    pub fn get(&self, index: usize) -> Option<&T> {
+        match index < self.dataset.len() && !self.is_removed(index) {
+            true => self.dataset.get(index),
+            false => None,
+        }
+    }
+
+    /// Insert a point into the dataset. Its index follows every existing point, including removed points which have not yet been
+    /// [compacted](Parallel::compact).
+    ///
+    /// The point is partitioned using the existing exclusion zones and appended to the last block, so it is immediately visible to queries.
+    /// A new block is started once the last block holds `block_size` points.
+    /// Exclusion zones are not regenerated; if inserted points are distributed very differently to the original dataset,
+    /// the zones may partition them poorly and queries will slow down.
+    pub fn insert(&mut self, point: T) {
+        self.extend(std::iter::once(point))
+    }
+
+    /// Insert every point in `points` into the dataset, in order.
+    ///
+    /// See [`insert`](Parallel::insert) for details.
+    pub fn extend(&mut self, points: impl IntoIterator<Item = T>) {
+        let points = points.into_iter().collect::<Vec<_>>();
+        let mut len = self.dataset.len();
+        let mut rest = points.as_slice();
+
+        while !rest.is_empty() {
+            // Queries index blocks by `block_idx * block_size`, so only the last block may be partially filled.
+            let offset = len % self.block_size;
+            if offset == 0 {
+                self.bitset
+                    .push(vec![BitVec::zeros(0); self.exclusions.len()]);
+                self.tombstones.push(BitVec::zeros(0));
+            }
+
+            let (chunk, remaining) = rest.split_at(rest.len().min(self.block_size - offset));
+            self.bitset
+                .last_mut()
+                .unwrap()
+                .par_iter_mut()
+                .zip(self.exclusions.par_iter())
+                .for_each(|(bv, ez)| {
+                    bv.resize(offset + chunk.len(), false);
+                    for (idx, pt) in chunk.iter().enumerate() {
+                        bv.set(offset + idx, ez.is_in(pt));
+                    }
+                });
+            self.tombstones
+                .last_mut()
+                .unwrap()
+                .resize(offset + chunk.len(), false);
+
+            len += chunk.len();
+            rest = remaining;
+        }
+
+        self.dataset.extend(points);
+    }
+
+    /// Remove the point at `index` from the dataset, returning `false` if it is out of bounds or was already removed.
+    ///
+    /// The point is marked with a tombstone and excluded from every query, but still occupies memory until [`compact`](Parallel::compact)
+    /// is called. Indices of other points are unchanged.
+    pub fn remove(&mut self, index: usize) -> bool {
+        if index >= self.dataset.len() || self.is_removed(index) {
+            return false;
+        }
+
+        self.tombstones[index / self.block_size].set(index % self.block_size, true);
+        true
+    }
+
+    /// Drop every [removed](Parallel::remove) point from the dataset and partitioning data.
+    ///
+    /// Remaining points keep their relative order, but their indices are shifted down to fill the gaps left by removed points.
+    /// Blocks are rebuilt so that every block except the last holds `block_size` points.
+    pub fn compact(&mut self) {
+        let removed = (0..self.dataset.len())
+            .map(|idx| self.is_removed(idx))
+            .collect::<Vec<_>>();
+        let keep = (0..self.dataset.len())
+            .filter(|idx| !removed[*idx])
+            .collect::<Vec<_>>();
+
+        self.bitset = keep
+            .par_chunks(self.block_size)
+            .map(|rows| {
+                (0..self.exclusions.len())
+                    .map(|col| {
+                        BitVec::from_bool_iterator(rows.iter().map(|&idx| {
+                            self.bitset[idx / self.block_size][col]
+                                .get_unchecked(idx % self.block_size)
+                        }))
+                    })
+                    .collect()
             })
-            .collect()
+            .collect();
+        self.tombstones = keep
+            .chunks(self.block_size)
+            .map(|rows| BitVec::zeros(rows.len()))
+            .collect();
+
+        let mut iter = removed.into_iter();
+        self.dataset.retain(|_| !iter.next().unwrap());
     }
 
-    fn sheet_exclusions(
-        _builder: &Builder<T>,
-        ref_points: &[T],
-    ) -> Vec<Box<dyn ExclusionSync<T> + 'a>> {
-        ref_points
+    /// Returns `true` if the point at `index` has been removed.
+    fn is_removed(&self, index: usize) -> bool {
+        self.tombstones[index / self.block_size].get_unchecked(index % self.block_size)
+    }
+
+    /// Perform a range search for every point in `points`, with the same radius `threshold` for each query.
+    ///
+    /// Results are returned in the same order as `points`. See [`range_search_batch_indices`](Parallel::range_search_batch_indices) for details.
+    pub fn range_search_batch(
+        &self,
+        points: &[T],
+        threshold: f64,
+    ) -> Result<Vec<Vec<(T, f64)>>, ParallelError> {
+        let queries = points
             .iter()
-            .combinations(2)
-            .map(|x| {
-                Box::new(SheetExclusion::new(x[0].clone(), x[1].clone(), 0.0))
-                    as Box<dyn ExclusionSync<T>>
+            .map(|pt| (pt.clone(), threshold))
+            .collect::<Vec<_>>();
+
+        self.range_search_batch_thresholds(&queries)
+    }
+
+    /// Perform a range search for every `(point, threshold)` pair in `queries`.
+    ///
+    /// Results are returned in the same order as `queries`. See [`range_search_batch_indices`](Parallel::range_search_batch_indices) for details.
+    pub fn range_search_batch_thresholds(
+        &self,
+        queries: &[(T, f64)],
+    ) -> Result<Vec<Vec<(T, f64)>>, ParallelError> {
+        Ok(self
+            .range_search_batch_indices(queries)?
+            .into_par_iter()
+            .map(|res| {
+                res.into_iter()
+                    .map(|(idx, d)| (self.dataset.get(idx).unwrap().clone(), d))
+                    .collect()
             })
-            .collect()
+            .collect())
+    }
+
+    /// Perform a range search for every `(point, threshold)` pair in `queries`, returning the index of each point in the dataset.
+    ///
+    /// Results are returned in the same order as `queries`.
+    ///
+    /// Unlike [`range_search`](BitPart::range_search), which parallelises the work of a single query, queries in a batch are
+    /// distributed across threads and each query is evaluated sequentially. The bitvectors used to filter candidate points are
+    /// allocated once per thread and reused between queries.
+    pub fn range_search_batch_indices(
+        &self,
+        queries: &[(T, f64)],
+    ) -> Result<Vec<Vec<(usize, f64)>>, ParallelError> {
+        if self.exclusions.is_empty() {
+            return Err(ParallelError::NoZones);
+        }
+
+        let res = queries
+            .par_iter()
+            .map_init(
+                || (BitVec::zeros(0), BitVec::zeros(0)),
+                |(ands, nots), (point, threshold)| {
+                    let mut ins = vec![];
+                    let mut outs = vec![];
+
+                    for (idx, ez) in self.exclusions.iter().enumerate() {
+                        if ez.must_be_in(point, *threshold) {
+                            ins.push(idx);
+                        } else if ez.must_be_out(point, *threshold) {
+                            outs.push(idx);
+                        }
+                    }
+
+                    let mut res = vec![];
+                    for (block_idx, bitvecs) in self.bitset.iter().enumerate() {
+                        // SAFETY: Queries can only be made if there are still exclusion zones left.
+                        let len = bitvecs[0].len();
+
+                        ands.resize(len, true);
+                        ands.set_all_true();
+                        for idx in &ins {
+                            ands.and_inplace(&bitvecs[*idx]);
+                        }
+
+                        // Removed points are excluded from every query
+                        nots.resize(len, false);
+                        nots.set_all_false();
+                        nots.or_inplace(&self.tombstones[block_idx]);
+                        for idx in &outs {
+                            nots.or_inplace(&bitvecs[*idx]);
+                        }
+
+                        res.extend(
+                            ands.difference_cloned(nots)
+                                .into_usizes()
+                                .into_iter()
+                                .map(|i| (block_idx * self.block_size) + i)
+                                .map(|idx| (idx, point.distance(self.dataset.get(idx).unwrap())))
+                                .filter(|(_, d)| d <= threshold),
+                        );
+                    }
+
+                    res
+                },
+            )
+            .collect();
+
+        Ok(res)
+    }
+
+    /// Returns the indices of points which could not be excluded from a range search.
+    fn candidates(&self, point: &T, threshold: f64) -> Result<Vec<usize>, ParallelError> {
+        let (ins, outs) = self.partition(point, threshold)?;
+        Ok(self.filter(&ins, &outs))
+    }
+
+    /// Returns the indices of zones which `point` must be in, and must be out of.
+    fn partition(
+        &self,
+        point: &T,
+        threshold: f64,
+    ) -> Result<(Vec<usize>, Vec<usize>), ParallelError> {
+        if self.exclusions.is_empty() {
+            return Err(ParallelError::NoZones);
+        }
+
+        let (ins, outs): (Vec<usize>, Vec<usize>) = self
+            .exclusions
+            .par_iter()
+            .enumerate()
+            .filter_map(|(idx, ez)| {
+                if ez.must_be_in(point, threshold) {
+                    Some(Either::Left(idx))
+                } else if ez.must_be_out(point, threshold) {
+                    Some(Either::Right(idx))
+                } else {
+                    None
+                }
+            })
+            .partition_map(|x| x);
+
+        Ok((ins, outs))
+    }
+
+    /// Returns the indices of points which are in every zone of `ins`, in no zone of `outs`, and have not been removed.
+    fn filter(&self, ins: &[usize], outs: &[usize]) -> Vec<usize> {
+        self.bitset
+            .par_iter()
+            .enumerate()
+            .flat_map(|(block_idx, bitvecs)| {
+                // SAFETY: Queries can only be made if there are still exclusion zones left.
+                let len = bitvecs[0].len();
+
+                let ands = ins
+                    .iter()
+                    .map(|idx| bitvecs.get(*idx).unwrap())
+                    .fold(BitVec::ones(len), |acc, v| acc & v); // TODO: fold or reduce?
+
+                // Removed points are excluded from every query
+                let nots = !outs
+                    .iter()
+                    .map(|idx| bitvecs.get(*idx).unwrap())
+                    .fold(self.tombstones[block_idx].clone(), |acc, v| acc | v);
+
+                let res = ands & nots;
+
+                res.into_usizes()
+                    .into_iter()
+                    .map(|internal_idx| (block_idx * self.block_size) + internal_idx)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
     }
 
     fn make_bitset(
         _block_size: usize,
         builder: &Builder<T>,
-        exclusions: &[Box<dyn ExclusionSync<T> + 'a>],
+        exclusions: &[Zone<T>],
     ) -> Vec<Vec<BitVec>> {
         builder
             .dataset
@@ -179,39 +458,54 @@ where
             .collect()
     }
 
-    fn ratio(&self, ones: usize) -> f64 {
-        ones as f64 / self.dataset.len() as f64
-    }
-
     /// Cull exclusion zones with low exclusion power.
     /// This function will compare all zones in in the data structure with one another and calculate their [Hamming distance](https://en.wikipedia.org/wiki/Hamming_distance).
-    /// If a zone's similarity ratio is above the given `threshold`, it is marked for removal.
+    /// If a zone's similarity ratio to an earlier zone is above the given `threshold`, it is marked for removal.
+    ///
+    /// Zones are compared in parallel, directly on the words of each block, and counting stops as soon as a pair is known to be dissimilar.
+    /// To cull zones before their partitioning data is built, use [`Builder::cull_by_similarity`].
     pub fn cull_by_similarity(&mut self, threshold: f64) {
-        let mut to_cull = HashSet::new();
-        for indices in (0..self.exclusions.len()).combinations(2) {
-            let i = indices[0];
-            let j = indices[1];
-            let hamming = {
-                self.bitset
-                    .iter()
-                    .map(|bvs| (bvs[i].xor_cloned(&bvs[j])).count_ones())
-                    .sum::<usize>()
-            };
-
-            if 1.0 - self.ratio(hamming) > threshold {
-                to_cull.insert(j);
-            }
-        }
+        let to_cull = cull::by_similarity(&self.columns(), threshold);
+        self.cull(to_cull)
+    }
 
+    /// Cull every exclusion zone except the `keep` most independent zones, instead of culling zones above a fixed similarity threshold.
+    ///
+    /// Zones are chosen greedily: first the zone which splits the dataset most evenly, and then repeatedly the zone whose column is farthest,
+    /// by [Hamming distance](https://en.wikipedia.org/wiki/Hamming_distance), from the column of its nearest chosen zone. A zone and its
+    /// complement partition the dataset identically, so are treated as equal.
+    ///
+    /// To cull zones before their partitioning data is built, use [`Builder::cull_to_independent`].
+    pub fn cull_to_independent(&mut self, keep: usize) {
+        let to_cull = cull::to_independent(&self.columns(), keep);
         self.cull(to_cull)
     }
 
+    /// Returns the words of every column, split into blocks.
+    fn columns(&self) -> cull::Columns<'_> {
+        cull::Columns::new(
+            (0..self.exclusions.len())
+                .map(|idx| {
+                    self.bitset
+                        .iter()
+                        .map(|bvs| cull::words(&bvs[idx]))
+                        .collect()
+                })
+                .collect(),
+            self.bitset
+                .iter()
+                .map(|bvs| bvs.first().map_or(0, BitVec::len))
+                .collect(),
+        )
+    }
+
     /// Cull exclusion zones with low exclusion power.
     /// This function measures the exclusion power of a zone by counting the ratio of points that are in/out to the dataset.
     /// If either ratio is above the `threshold` given, it is marked for removal.
+    ///
+    /// To cull zones before their partitioning data is built, use [`Builder::cull_by_popcnt`].
     pub fn cull_by_popcnt(&mut self, threshold: f64) {
         let len = self.exclusions.len();
-        let mut to_cull = HashSet::new();
 
         // Count ones for each column, across all the blocks.
         let popcnt = self.bitset.iter().fold(vec![0_usize; len], |acc, x| {
@@ -221,43 +515,31 @@ where
                 .collect()
         });
 
-        for (idx, cnt) in popcnt.into_iter().enumerate() {
-            if self.ratio(cnt) > threshold || self.ratio(self.dataset.len() - cnt) > threshold {
-                to_cull.insert(idx);
-            }
-        }
-
-        self.cull(to_cull)
+        self.cull(cull::by_popcnt(popcnt, self.dataset.len(), threshold))
     }
 
     fn cull(&mut self, to_cull: HashSet<usize>) {
-        let keep = (0..self.exclusions.len())
-            .map(|idx| !to_cull.contains(&idx))
-            .collect::<Vec<_>>();
-
         for bvs in self.bitset.iter_mut() {
-            let mut iter = keep.iter();
-
-            bvs.retain(|_| *iter.next().unwrap());
+            cull::retain(bvs, &to_cull);
         }
-
-        let mut iter = keep.iter();
-        self.exclusions.retain(|_| *iter.next().unwrap());
+        cull::retain(&mut self.exclusions, &to_cull);
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::metric::Euclidean;
-    use sisap_data::{colors::parse_colors, nasa::parse_nasa, parser::parse};
+    use crate::{
+        exclusions::tests::Dimension, metric::Euclidean, tests::nasa_fixture, Offset, Radii,
+        Selection,
+    };
+    use sisap_data::{colors::parse_colors, nasa::NASA_DIMENSION, parser::parse};
     use std::fs;
 
     use super::*;
 
-    pub(crate) const NASA: &str = include_str!("../sisap-data/src/nasa.ascii");
     pub(crate) const COLORS: &str = include_str!("../sisap-data/src/colors.ascii");
 
-    fn test<T>(dataset: &Vec<T>, bitpart: &Parallel<T>, query: T, threshold: f64)
+    fn test<T>(dataset: &[T], bitpart: &Parallel<T>, query: T, threshold: f64)
     where
         for<'a> T: Metric + Send + Sync + 'a,
     {
@@ -270,7 +552,7 @@ mod tests {
 
         // Check results match up with linear search
         let brute_force = dataset
-            .into_iter()
+            .iter()
             .map(|pt| pt.distance(&query))
             .filter(|d| *d <= threshold)
             .count();
@@ -278,32 +560,124 @@ mod tests {
         assert_eq!(res.len(), brute_force);
     }
 
+    fn test_knn(
+        bitpart: &Parallel<Euclidean<Vec<f64>>>,
+        query: Euclidean<Vec<f64>>,
+        nn: &[(usize, f64)],
+    ) {
+        // The query point itself is always its own nearest neighbour, and is not included in `nn`.
+        let res = bitpart.knn_search(query, nn.len() + 1).unwrap();
+
+        assert_eq!(res[0].1, 0.0);
+        assert_eq!(
+            res[1..].iter().map(|(_, d)| *d).collect::<Vec<_>>(),
+            nn.iter().map(|(_, d)| *d).collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn sisap_nasa_par() {
-        let nasa = parse_nasa(NASA)
-            .unwrap()
-            .into_iter()
-            .map(Euclidean::new)
-            .collect::<Vec<_>>();
+        let (nasa, builder, query, threshold) = nasa_fixture();
 
-        let bitpart = Builder::new(nasa.clone(), 40).build_parallel(Some(512));
-        let query = nasa[317].clone();
-        let threshold = 1.0;
+        let bitpart = builder.build_parallel(Some(512));
 
         test(&nasa, &bitpart, query.clone(), threshold);
     }
 
     #[test]
-    fn sisap_nasa_par_cull_popcnt() {
-        let nasa = parse_nasa(NASA)
-            .unwrap()
-            .into_iter()
-            .map(Euclidean::new)
+    fn sisap_nasa_par_farthest_first() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = builder
+            .selection(Selection::FarthestFirst)
+            .build_parallel(Some(512));
+
+        test(&nasa, &bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_par_custom() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = (0..NASA_DIMENSION)
+            .fold(builder, |builder, dim| {
+                builder.exclusion(Dimension { dim, value: 0.0 })
+            })
+            .build_parallel(Some(512));
+        assert_eq!(bitpart.zones(), 40 * 5 + 780 + NASA_DIMENSION);
+
+        test(&nasa, &bitpart, query, threshold);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn sisap_nasa_par_serde() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let mut bitpart = builder.build_parallel(Some(512));
+        assert!(bitpart.remove(0));
+        let json = serde_json::to_string(&bitpart).unwrap();
+        let bitpart: Parallel<Euclidean<sisap_data::nasa::Nasa>> =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(bitpart.zones(), 40 * 5 + 780);
+
+        test(&nasa[1..], &bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_par_indices() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = builder.build_parallel(Some(512));
+
+        let res = bitpart
+            .range_search_indices(query.clone(), threshold)
+            .unwrap();
+
+        // Check indices refer to the same points in the dataset
+        assert!(res
+            .iter()
+            .all(|(idx, d)| bitpart.get(*idx).unwrap().distance(&query) == *d
+                && nasa[*idx].distance(&query) == *d));
+
+        // Check results match up with linear search
+        let brute_force = (0..nasa.len())
+            .filter(|idx| nasa[*idx].distance(&query) <= threshold)
             .collect::<Vec<_>>();
 
-        let mut bitpart = Builder::new(nasa.clone(), 40).build_parallel(Some(512));
-        let query = nasa[317].clone();
-        let threshold = 1.0;
+        assert_eq!(
+            res.into_iter().map(|(idx, _)| idx).collect::<Vec<_>>(),
+            brute_force
+        );
+    }
+
+    #[test]
+    fn sisap_nasa_par_stats() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = builder.build_parallel(Some(512));
+
+        let (res, stats) = bitpart
+            .range_search_stats(query.clone(), threshold)
+            .unwrap();
+        assert_eq!(
+            res,
+            bitpart
+                .range_search_indices(query.clone(), threshold)
+                .unwrap()
+        );
+
+        assert_eq!(stats.zones, bitpart.zones());
+        assert!(stats.must_be_in + stats.must_be_out <= stats.zones);
+        assert_eq!(stats.results, res.len());
+        assert!(stats.candidates >= stats.results && stats.candidates < nasa.len());
+    }
+
+    #[test]
+    fn sisap_nasa_par_cull_popcnt() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let mut bitpart = builder.build_parallel(Some(512));
 
         bitpart.cull_by_popcnt(0.95);
         test(&nasa, &bitpart, query, threshold);
@@ -311,20 +685,40 @@ mod tests {
 
     #[test]
     fn sisap_nasa_par_cull_similarity() {
-        let nasa = parse_nasa(NASA)
-            .unwrap()
-            .into_iter()
-            .map(Euclidean::new)
-            .collect::<Vec<_>>();
+        let (nasa, builder, query, threshold) = nasa_fixture();
 
-        let mut bitpart = Builder::new(nasa.clone(), 40).build_parallel(Some(512));
-        let query = nasa[317].clone();
-        let threshold = 1.0;
+        let mut bitpart = builder.build_parallel(Some(512));
 
         bitpart.cull_by_similarity(0.95);
         test(&nasa, &bitpart, query, threshold);
     }
 
+    #[test]
+    fn sisap_nasa_par_cull_independent() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let mut bitpart = builder.build_parallel(Some(512));
+
+        bitpart.cull_to_independent(100);
+        assert_eq!(bitpart.zones(), 100);
+        test(&nasa, &bitpart, query, threshold);
+    }
+
+    #[test]
+    fn cull_similarity_blocks() {
+        let (_, builder, _, _) = nasa_fixture();
+        let builder = builder.ref_points(20);
+
+        // Blocks which do not end on a word boundary give the same Hamming distances as a single column
+        let mut parallel = builder.clone().build_parallel(Some(500));
+        let mut sequential = builder.build();
+        parallel.cull_by_similarity(0.9);
+        sequential.cull_by_similarity(0.9);
+
+        assert!(parallel.zones() < 20 * 5 + 190);
+        assert_eq!(parallel.zones(), sequential.zones());
+    }
+
     #[test]
     fn sisap_colors_par() {
         let colors = parse_colors(COLORS)
@@ -340,6 +734,67 @@ mod tests {
         test(&colors, &bitpart, query.clone(), threshold);
     }
 
+    #[test]
+    fn sisap_colors_par_radii_global() {
+        let colors = parse_colors(COLORS)
+            .unwrap()
+            .into_iter()
+            .map(Euclidean::new)
+            .collect::<Vec<_>>();
+
+        let bitpart = Builder::new(colors.clone(), 40)
+            .radii(Radii::Global)
+            .build_parallel(Some(512));
+        let query = colors[70446].clone();
+        let threshold = 0.05;
+
+        test(&colors, &bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_par_radii_median() {
+        let (nasa, builder, _, _) = nasa_fixture();
+
+        let bitpart = builder
+            .radii(Radii::PerPoint)
+            .radii_per_point(1)
+            .build_parallel(Some(512));
+
+        // Each ball is centred on the median distance, so should split the dataset roughly in half
+        for zone in 0..40 {
+            let ones = bitpart
+                .bitset
+                .iter()
+                .map(|bvs| bvs[zone].count_ones())
+                .sum::<usize>();
+            let ratio = ones as f64 / nasa.len() as f64;
+            assert!((0.4..0.6).contains(&ratio), "{ratio}");
+        }
+    }
+
+    #[test]
+    fn sisap_nasa_par_offset_median() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = builder
+            .radii_per_point(0)
+            .offset(Offset::Median)
+            .build_parallel(Some(512));
+
+        // Each sheet is offset by the median, so should split the dataset roughly in half
+        for zone in 0..bitpart.exclusions.len() {
+            let ones = bitpart
+                .bitset
+                .iter()
+                .map(|bvs| bvs[zone].count_ones())
+                .sum::<usize>();
+            let ratio = ones as f64 / nasa.len() as f64;
+            assert!((0.4..0.6).contains(&ratio), "{ratio}");
+        }
+
+        test(&nasa, &bitpart, query, threshold);
+    }
+
     #[test]
     fn sisap_colors_par_cull_popcnt() {
         let colors = parse_colors(COLORS)
@@ -405,7 +860,7 @@ mod tests {
         let queries = points
             .iter()
             .cloned()
-            .zip(nns.into_iter())
+            .zip(nns)
             .map(|(pt, nn)| (pt, nn.last().unwrap().1))
             .take(1000)
             .collect::<Vec<_>>();
@@ -417,6 +872,143 @@ mod tests {
         }
     }
 
+    #[test]
+    fn sisap_nasa_par_extend() {
+        let (nasa, _, _, threshold) = nasa_fixture();
+
+        // The first half does not fill its last block, so inserted points are split between it and new blocks
+        let (first, rest) = nasa.split_at(nasa.len() / 2);
+        let mut bitpart = Builder::new(first.to_vec(), 40).build_parallel(Some(512));
+        bitpart.insert(rest[0].clone());
+        bitpart.extend(rest[1..].iter().cloned());
+        assert_eq!(bitpart.len(), nasa.len());
+        assert_eq!(bitpart.bitset.len(), nasa.len().div_ceil(512));
+        assert_eq!(
+            bitpart
+                .get(nasa.len() - 1)
+                .unwrap()
+                .distance(&nasa[nasa.len() - 1]),
+            0.0
+        );
+
+        let query = nasa[nasa.len() - 317].clone();
+        test(&nasa, &bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_par_remove() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        // Remove every other point
+        let mut bitpart = builder.build_parallel(Some(512));
+        for idx in (0..nasa.len()).step_by(2) {
+            assert!(bitpart.remove(idx));
+        }
+        assert!(!bitpart.remove(0));
+        assert!(!bitpart.remove(nasa.len()));
+        assert!(bitpart.get(0).is_none());
+        assert_eq!(bitpart.len(), nasa.len() / 2);
+
+        let remaining = nasa.iter().skip(1).step_by(2).cloned().collect::<Vec<_>>();
+        let brute_force = remaining
+            .iter()
+            .filter(|pt| pt.distance(&query) <= threshold)
+            .count();
+
+        let res = bitpart
+            .range_search_indices(query.clone(), threshold)
+            .unwrap();
+        assert!(res.iter().all(|(idx, _)| idx % 2 == 1));
+        assert_eq!(res.len(), brute_force);
+
+        let batch = bitpart
+            .range_search_batch_indices(&[(query.clone(), threshold)])
+            .unwrap();
+        assert_eq!(batch[0], res);
+
+        let res = bitpart.knn_search_indices(query.clone(), 10).unwrap();
+        assert_eq!(res.len(), 10);
+        assert!(res.iter().all(|(idx, _)| idx % 2 == 1));
+
+        bitpart.compact();
+        assert_eq!(bitpart.len(), remaining.len());
+        assert_eq!(bitpart.get(0).unwrap().distance(&remaining[0]), 0.0);
+
+        test(&remaining, &bitpart, query, threshold);
+    }
+
+    #[test]
+    fn try_build_parallel_zero_block_size() {
+        let points = (0..100)
+            .map(|x| Euclidean::new([x as f64]))
+            .collect::<Vec<_>>();
+
+        assert!(matches!(
+            Builder::new(points.clone(), 4).try_build_parallel(Some(0)),
+            Err(BuildError::ZeroBlockSize)
+        ));
+        assert!(Builder::new(points, 4).try_build_parallel(None).is_ok());
+    }
+
+    #[test]
+    fn nearest_neighbour_batch() {
+        let points = parse(&fs::read_to_string("data/100k_d20_flat.ascii").unwrap())
+            .unwrap()
+            .1
+             .1
+            .into_iter()
+            .map(Euclidean::new)
+            .collect::<Vec<_>>();
+
+        let nns: Vec<Vec<(usize, f64)>> =
+            serde_json::from_str(&fs::read_to_string("data/100k_d20_flat.json").unwrap()).unwrap();
+
+        let queries = points
+            .iter()
+            .cloned()
+            .zip(nns.iter())
+            .map(|(pt, nn)| (pt, nn.last().unwrap().1))
+            .take(1000)
+            .collect::<Vec<_>>();
+
+        let bitpart = Builder::new(points.clone(), 40).build_parallel(Some(8192));
+
+        let res = bitpart.range_search_batch_indices(&queries).unwrap();
+        assert_eq!(res.len(), queries.len());
+
+        // Each query finds itself, followed by its nearest neighbours
+        for (mut res, nn) in res.into_iter().zip(nns) {
+            res.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+            assert_eq!(
+                res.into_iter().map(|(_, d)| d).collect::<Vec<_>>(),
+                [0.0]
+                    .into_iter()
+                    .chain(nn.into_iter().map(|(_, d)| d))
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn nearest_neighbour_knn() {
+        let points = parse(&fs::read_to_string("data/100k_d20_flat.ascii").unwrap())
+            .unwrap()
+            .1
+             .1
+            .into_iter()
+            .map(Euclidean::new)
+            .collect::<Vec<_>>();
+
+        let nns: Vec<Vec<(usize, f64)>> =
+            serde_json::from_str(&fs::read_to_string("data/100k_d20_flat.json").unwrap()).unwrap();
+
+        let bitpart = Builder::new(points.clone(), 40).build_parallel(Some(8192));
+
+        for (query, nn) in points.iter().cloned().zip(nns.iter()).take(1000) {
+            test_knn(&bitpart, query, nn);
+        }
+    }
+
     #[test]
     fn nearest_neighbour_cull_popcnt() {
         let points = parse(&fs::read_to_string("data/100k_d20_flat.ascii").unwrap())
@@ -433,7 +1025,7 @@ mod tests {
         let queries = points
             .iter()
             .cloned()
-            .zip(nns.into_iter())
+            .zip(nns)
             .map(|(pt, nn)| (pt, nn.last().unwrap().1))
             .take(1000)
             .collect::<Vec<_>>();
@@ -462,7 +1054,7 @@ mod tests {
         let queries = points
             .iter()
             .cloned()
-            .zip(nns.into_iter())
+            .zip(nns)
             .map(|(pt, nn)| (pt, nn.last().unwrap().1))
             .take(1000)
             .collect::<Vec<_>>();
@@ -492,10 +1084,24 @@ where
     ///
     /// # Panics
     ///
-    /// This function will panic if `block_size` is set to `Some(0)`. Use `None` if you want bitvector operations to be performed on a single thread.
-    pub fn build_parallel<'a>(self, block_size: Option<usize>) -> Parallel<'a, T> {
-        assert!(block_size != Some(0));
-        Parallel::setup(self, block_size)
+    /// This function will panic if the builder's parameters are invalid, or if `block_size` is set to `Some(0)`. Use `None` if you want
+    /// bitvector operations to be performed on a single thread.
+    /// See [`try_build_parallel`](crate::builder::Builder::try_build_parallel) for a non-panicking alternative.
+    pub fn build_parallel(self, block_size: Option<usize>) -> Parallel<T> {
+        self.try_build_parallel(block_size)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Construct a [`Parallel`](crate::Parallel), returning an error if the builder's parameters are invalid
+    /// or `block_size` is set to `Some(0)`.
+    ///
+    /// See [`build_parallel`](crate::builder::Builder::build_parallel) for an explanation of `block_size`.
+    pub fn try_build_parallel(self, block_size: Option<usize>) -> Result<Parallel<T>, BuildError> {
+        if block_size == Some(0) {
+            return Err(BuildError::ZeroBlockSize);
+        }
+        self.validate()?;
+        Ok(Parallel::setup(self, block_size))
     }
 }
 
@@ -504,8 +1110,10 @@ where
 pub enum ParallelError {
     /// There are no exclusion zones in the data structure.
     ///
-    /// This error can only occur if [`cull_by_similarity`](crate::Parallel::cull_by_similarity) and/or
-    /// [`cull_by_similarity`](crate::Parallel::cull_by_popcnt) was used and no exclusion zones are left.
+    /// This error occurs if the data structure was built with [`generate_exclusions(false)`](crate::Builder::generate_exclusions)
+    /// and no [custom exclusion zones](crate::Builder::exclusion), or if culling with [`cull_by_popcnt`](crate::Parallel::cull_by_popcnt),
+    /// [`cull_by_similarity`](crate::Parallel::cull_by_similarity) or [`cull_to_independent`](crate::Parallel::cull_to_independent)
+    /// (or their [`Builder`] equivalents) left no exclusion zones.
     ///
     /// **If this error is encountered, the data structure cannot be used again.**
     #[error("no exclusion zones defined")]
@@ -1,65 +1,119 @@
+use std::collections::HashSet;
 use std::convert::Infallible;
+use std::time::Instant;
 
 use crate::builder::Builder;
-use crate::exclusions::{BallExclusion, Exclusion, SheetExclusion};
+use crate::cull;
+use crate::exclusions::{Exclusion, Zone};
+use crate::knn;
 use crate::metric::Metric;
-use crate::BitPart;
+use crate::{BitPart, QueryStats, RangeStats};
 
 use bitvec_simd::BitVec;
-use itertools::Itertools;
 
 /// Sequential BitPart.
 ///
 /// This is essentially a Rust port of the [reference library](https://github.com/aldearle/BitPart) written in Java.
 ///
 /// Explicit SIMD optimisations for bitvector operations are provided by [`bitvec_simd`](bitvec_simd).
-pub struct Sequential<'a, T> {
+///
+/// With the `serde` feature enabled, the index (dataset, exclusion zones and bitset columns) can be serialized and
+/// deserialized. Serialization fails if the index was built with custom exclusion zones.
+///
+/// `E` is the type of custom exclusion zones, which is only `dyn Exclusion<T>` if they were added with
+/// [`Builder::local_exclusion`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Sequential<T, E: ?Sized = dyn Exclusion<T> + Send + Sync> {
     dataset: Vec<T>,
-    exclusions: Vec<Box<dyn Exclusion<T> + 'a>>,
+    exclusions: Vec<Zone<T, E>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::columns"))]
     bitset: Vec<BitVec>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::columns"))]
+    tombstones: BitVec,
 }
 
-impl<T> BitPart<T> for Sequential<'_, T>
+impl<T, E> BitPart<T> for Sequential<T, E>
 where
     T: Metric,
+    E: Exclusion<T> + ?Sized,
 {
     type Error = Infallible;
 
     fn range_search(&self, point: T, threshold: f64) -> Result<Vec<(T, f64)>, Self::Error> {
-        let mut ins = vec![];
-        let mut outs = vec![];
-
-        for (idx, ez) in self.exclusions.iter().enumerate() {
-            if ez.must_be_in(&point, threshold) {
-                ins.push(idx);
-            } else if ez.must_be_out(&point, threshold) {
-                outs.push(idx);
-            }
-        }
+        Ok(self
+            .range_search_indices(point, threshold)?
+            .into_iter()
+            .map(|(i, dist)| (self.dataset.get(i).unwrap().clone(), dist))
+            .collect())
+    }
 
-        let ands: BitVec = ins
-            .iter()
-            .map(|&i| self.bitset.get(i).unwrap())
-            .fold(BitVec::ones(self.dataset.len()), |acc, v| acc & v);
+    fn range_search_indices(
+        &self,
+        point: T,
+        threshold: f64,
+    ) -> Result<Vec<(usize, f64)>, Self::Error> {
+        Ok(self
+            .candidates(&point, threshold)
+            .into_iter()
+            .map(|i| (i, point.distance(self.dataset.get(i).unwrap())))
+            .filter(|(_, dist)| *dist <= threshold)
+            .collect())
+    }
 
-        let nots: BitVec = !outs
-            .iter()
-            .map(|&i| self.bitset.get(i).unwrap())
-            .fold(BitVec::zeros(self.dataset.len()), |acc, v| acc | v);
+    fn range_search_stats(&self, point: T, threshold: f64) -> Result<RangeStats, Self::Error> {
+        let start = Instant::now();
+        let (ins, outs) = self.partition(&point, threshold);
+        let mut stats = QueryStats::new(&self.exclusions, &ins, &outs, start.elapsed());
 
-        let candidates = ands & nots;
+        let start = Instant::now();
+        let candidates = self.filter(&ins, &outs);
+        stats.filter_time = start.elapsed();
+        stats.candidates = candidates.len();
 
-        Ok(candidates
-            .into_usizes()
+        let start = Instant::now();
+        let res = candidates
             .into_iter()
-            .map(|i| self.dataset.get(i).unwrap())
-            .map(|pt| (pt.clone(), pt.distance(&point)))
+            .map(|i| (i, point.distance(self.dataset.get(i).unwrap())))
             .filter(|(_, dist)| *dist <= threshold)
+            .collect::<Vec<_>>();
+        stats.distance_time = start.elapsed();
+        stats.results = res.len();
+
+        Ok((res, stats))
+    }
+
+    fn knn_search(&self, point: T, k: usize) -> Result<Vec<(T, f64)>, Self::Error> {
+        Ok(self
+            .knn_search_indices(point, k)?
+            .into_iter()
+            .map(|(i, dist)| (self.dataset.get(i).unwrap().clone(), dist))
             .collect())
     }
 
+    fn knn_search_indices(&self, point: T, k: usize) -> Result<Vec<(usize, f64)>, Self::Error> {
+        knn::search(
+            k,
+            self.dataset.len(),
+            |idx| self.tombstones.get_unchecked(idx),
+            |radius| Ok(self.candidates(&point, radius)),
+            |indices| {
+                Ok(indices
+                    .iter()
+                    .map(|&idx| point.distance(self.dataset.get(idx).unwrap()))
+                    .collect())
+            },
+        )
+    }
+
     fn len(&self) -> usize {
-        self.dataset.len()
+        self.dataset.len() - self.tombstones.count_ones()
     }
 
     fn zones(&self) -> usize {
@@ -67,61 +121,175 @@ where
     }
 
     fn is_empty(&self) -> bool {
-        self.dataset.len() == 0
+        self.len() == 0
     }
 }
 
-impl<'a, T> Sequential<'a, T>
+impl<T, E> Sequential<T, E>
 where
     T: Metric,
-    dyn Exclusion<T>: 'a,
+    E: Exclusion<T> + ?Sized,
 {
-    pub(crate) fn setup(builder: Builder<T>) -> Self {
-        // TODO: actually randomise this
-        let ref_points = &builder.dataset[0..(builder.ref_points as usize)];
-        let mut exclusions = Self::ball_exclusions(&builder, ref_points);
-        exclusions.extend(Self::sheet_exclusions(&builder, ref_points));
+    /// Returns a reference to the point at `index` in the dataset, or `None` if it is out of bounds or has been [removed](Sequential::remove).
+    ///
+    /// Indices are those returned by [`range_search_indices`](BitPart::range_search_indices) and
+    /// [`knn_search_indices`](BitPart::knn_search_indices), and match the order in which points were given to the [`Builder`].
+    pub fn get(&self, index: usize) -> Option<&T> {
+        match self.tombstones.get(index) {
+            Some(false) => self.dataset.get(index),
+            _ => None,
+        }
+    }
+
+    /// Insert a point into the dataset. Its index follows every existing point, including removed points which have not yet been
+    /// [compacted](Sequential::compact).
+    ///
+    /// The point is partitioned using the existing exclusion zones, so it is immediately visible to queries.
+    /// Exclusion zones are not regenerated; if inserted points are distributed very differently to the original dataset,
+    /// the zones may partition them poorly and queries will slow down.
+    pub fn insert(&mut self, point: T) {
+        self.extend(std::iter::once(point))
+    }
+
+    /// Insert every point in `points` into the dataset, in order.
+    ///
+    /// See [`insert`](Sequential::insert) for details.
+    pub fn extend(&mut self, points: impl IntoIterator<Item = T>) {
+        let points = points.into_iter().collect::<Vec<_>>();
+        let len = self.dataset.len();
+
+        for (ez, bv) in self.exclusions.iter().zip(self.bitset.iter_mut()) {
+            bv.resize(len + points.len(), false);
+            for (idx, pt) in points.iter().enumerate() {
+                bv.set(len + idx, ez.is_in(pt));
+            }
+        }
+
+        self.tombstones.resize(len + points.len(), false);
+        self.dataset.extend(points);
+    }
+
+    /// Remove the point at `index` from the dataset, returning `false` if it is out of bounds or was already removed.
+    ///
+    /// The point is marked with a tombstone and excluded from every query, but still occupies memory until [`compact`](Sequential::compact)
+    /// is called. Indices of other points are unchanged.
+    pub fn remove(&mut self, index: usize) -> bool {
+        match self.tombstones.get(index) {
+            Some(false) => {
+                self.tombstones.set(index, true);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Drop every [removed](Sequential::remove) point from the dataset and partitioning data.
+    ///
+    /// Remaining points keep their relative order, but their indices are shifted down to fill the gaps left by removed points.
+    pub fn compact(&mut self) {
+        let removed = self.tombstones.clone().into_bools();
+        let keep = (0..self.dataset.len())
+            .filter(|idx| !removed[*idx])
+            .collect::<Vec<_>>();
+
+        for bv in self.bitset.iter_mut() {
+            *bv = BitVec::from_bool_iterator(keep.iter().map(|&idx| bv.get_unchecked(idx)));
+        }
+        self.tombstones = BitVec::zeros(keep.len());
+
+        let mut iter = removed.into_iter();
+        self.dataset.retain(|_| !iter.next().unwrap());
+    }
+
+    /// Cull exclusion zones with low exclusion power.
+    /// This function compares every zone with every other zone and calculates the [Hamming distance](https://en.wikipedia.org/wiki/Hamming_distance) between their columns.
+    /// If a zone's similarity ratio to an earlier zone is above the given `threshold`, it is marked for removal.
+    ///
+    /// Counting stops as soon as a pair is known to be dissimilar. With the `par` feature enabled, zones are compared in parallel.
+    /// To cull zones before their partitioning data is built, use [`Builder::cull_by_similarity`].
+    pub fn cull_by_similarity(&mut self, threshold: f64) {
+        let to_cull = cull::by_similarity(&self.columns(), threshold);
+        self.cull(to_cull)
+    }
+
+    /// Cull every exclusion zone except the `keep` most independent zones, instead of culling zones above a fixed similarity threshold.
+    ///
+    /// See [`Builder::cull_to_independent`] for how zones are chosen, and to cull zones before their partitioning data is built.
+    pub fn cull_to_independent(&mut self, keep: usize) {
+        let to_cull = cull::to_independent(&self.columns(), keep);
+        self.cull(to_cull)
+    }
+
+    fn columns(&self) -> cull::Columns<'_> {
+        cull::Columns::single(self.bitset.iter().map(cull::words), self.dataset.len())
+    }
+
+    /// Cull exclusion zones with low exclusion power.
+    /// This function measures the exclusion power of a zone by counting the ratio of points that are in/out to the dataset.
+    /// If either ratio is above the `threshold` given, it is marked for removal.
+    ///
+    /// To cull zones before their partitioning data is built, use [`Builder::cull_by_popcnt`].
+    pub fn cull_by_popcnt(&mut self, threshold: f64) {
+        let popcnt = self.bitset.iter().map(BitVec::count_ones);
+
+        self.cull(cull::by_popcnt(popcnt, self.dataset.len(), threshold))
+    }
+
+    fn cull(&mut self, to_cull: HashSet<usize>) {
+        cull::retain(&mut self.bitset, &to_cull);
+        cull::retain(&mut self.exclusions, &to_cull);
+    }
+
+    pub(crate) fn setup(builder: Builder<T, E>) -> Self {
+        let exclusions = builder.zones();
         let bitset = Self::make_bitset(&builder, &exclusions);
         Self {
+            tombstones: BitVec::zeros(builder.dataset.len()),
             dataset: builder.dataset,
             bitset,
             exclusions,
         }
     }
 
-    fn ball_exclusions(builder: &Builder<T>, ref_points: &[T]) -> Vec<Box<dyn Exclusion<T> + 'a>> {
-        let radii = [
-            builder.mean_distance - 2.0 * builder.radius_increment,
-            builder.mean_distance - builder.radius_increment,
-            builder.mean_distance,
-            builder.mean_distance + builder.radius_increment,
-            builder.mean_distance + 2.0 * builder.radius_increment,
-        ];
+    /// Returns the indices of points which could not be excluded from a range search.
+    fn candidates(&self, point: &T, threshold: f64) -> Vec<usize> {
+        let (ins, outs) = self.partition(point, threshold);
+        self.filter(&ins, &outs)
+    }
+
+    /// Returns the indices of zones which `point` must be in, and must be out of.
+    fn partition(&self, point: &T, threshold: f64) -> (Vec<usize>, Vec<usize>) {
+        let mut ins = vec![];
+        let mut outs = vec![];
 
-        ref_points
-            .iter()
-            .cartesian_product(radii.into_iter())
-            .map(|(point, radius)| {
-                Box::new(BallExclusion::new(point.clone(), radius)) as Box<dyn Exclusion<T>>
-            })
-            .collect()
+        for (idx, ez) in self.exclusions.iter().enumerate() {
+            if ez.must_be_in(point, threshold) {
+                ins.push(idx);
+            } else if ez.must_be_out(point, threshold) {
+                outs.push(idx);
+            }
+        }
+
+        (ins, outs)
     }
 
-    fn sheet_exclusions(
-        _builder: &Builder<T>,
-        ref_points: &[T],
-    ) -> Vec<Box<dyn Exclusion<T> + 'a>> {
-        ref_points
+    /// Returns the indices of points which are in every zone of `ins`, in no zone of `outs`, and have not been removed.
+    fn filter(&self, ins: &[usize], outs: &[usize]) -> Vec<usize> {
+        let ands: BitVec = ins
             .iter()
-            .combinations(2)
-            .map(|x| {
-                Box::new(SheetExclusion::new(x[0].clone(), x[1].clone(), 0.0))
-                    as Box<dyn Exclusion<T>>
-            })
-            .collect()
+            .map(|&i| self.bitset.get(i).unwrap())
+            .fold(BitVec::ones(self.dataset.len()), |acc, v| acc & v);
+
+        // Removed points are excluded from every query
+        let nots: BitVec = !outs
+            .iter()
+            .map(|&i| self.bitset.get(i).unwrap())
+            .fold(self.tombstones.clone(), |acc, v| acc | v);
+
+        (ands & nots).into_usizes()
     }
 
-    fn make_bitset(builder: &Builder<T>, exclusions: &[Box<dyn Exclusion<T> + 'a>]) -> Vec<BitVec> {
+    fn make_bitset(builder: &Builder<T, E>, exclusions: &[Zone<T, E>]) -> Vec<BitVec> {
         exclusions
             .iter()
             .map(|ex| BitVec::from_bool_iterator(builder.dataset.iter().map(|pt| ex.is_in(pt))))
@@ -131,17 +299,21 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::metric::Euclidean;
-    use sisap_data::{colors::parse_colors, nasa::parse_nasa};
+    use crate::{
+        exclusions::tests::Dimension, metric::Euclidean, tests::nasa_fixture, Offset, Radii,
+        Selection,
+    };
+    use sisap_data::{colors::parse_colors, nasa::NASA_DIMENSION, parser::parse};
+    use std::fs;
 
     use super::*;
 
-    pub(crate) const NASA: &str = include_str!("../sisap-data/src/nasa.ascii");
     pub(crate) const COLORS: &str = include_str!("../sisap-data/src/colors.ascii");
 
-    fn test<T>(dataset: Vec<T>, bitpart: Sequential<T>, query: T, threshold: f64)
+    fn test<T, E>(dataset: Vec<T>, bitpart: Sequential<T, E>, query: T, threshold: f64)
     where
         for<'a> T: Metric + 'a,
+        E: Exclusion<T> + ?Sized,
     {
         let res = bitpart.range_search(query.clone(), threshold).unwrap();
 
@@ -154,23 +326,251 @@ mod tests {
         let brute_force = dataset
             .into_iter()
             .map(|pt| pt.distance(&query))
-            .filter(|d| *d < threshold)
+            .filter(|d| *d <= threshold)
+            .count();
+
+        assert_eq!(res.len(), brute_force);
+    }
+
+    fn test_knn(
+        bitpart: &Sequential<Euclidean<Vec<f64>>>,
+        query: Euclidean<Vec<f64>>,
+        nn: &[(usize, f64)],
+    ) {
+        // The query point itself is always its own nearest neighbour, and is not included in `nn`.
+        let res = bitpart.knn_search(query, nn.len() + 1).unwrap();
+
+        assert_eq!(res[0].1, 0.0);
+        assert_eq!(
+            res[1..].iter().map(|(_, d)| *d).collect::<Vec<_>>(),
+            nn.iter().map(|(_, d)| *d).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn sisap_nasa_extend() {
+        let (nasa, _, _, threshold) = nasa_fixture();
+
+        let (first, rest) = nasa.split_at(nasa.len() / 2);
+        let mut bitpart = Builder::new(first.to_vec(), 40).build();
+        bitpart.insert(rest[0].clone());
+        bitpart.extend(rest[1..].iter().cloned());
+        assert_eq!(bitpart.len(), nasa.len());
+        assert_eq!(
+            bitpart
+                .get(nasa.len() - 1)
+                .unwrap()
+                .distance(&nasa[nasa.len() - 1]),
+            0.0
+        );
+
+        let query = nasa[nasa.len() - 317].clone();
+        test(nasa, bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_remove() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        // Remove every other point
+        let mut bitpart = builder.build();
+        for idx in (0..nasa.len()).step_by(2) {
+            assert!(bitpart.remove(idx));
+        }
+        assert!(!bitpart.remove(0));
+        assert!(!bitpart.remove(nasa.len()));
+        assert!(bitpart.get(0).is_none());
+        assert_eq!(bitpart.len(), nasa.len() / 2);
+
+        let remaining = nasa.iter().skip(1).step_by(2).cloned().collect::<Vec<_>>();
+        let brute_force = remaining
+            .iter()
+            .filter(|pt| pt.distance(&query) <= threshold)
             .count();
 
+        let res = bitpart
+            .range_search_indices(query.clone(), threshold)
+            .unwrap();
+        assert!(res.iter().all(|(idx, _)| idx % 2 == 1));
         assert_eq!(res.len(), brute_force);
+
+        let res = bitpart.knn_search_indices(query.clone(), 10).unwrap();
+        assert_eq!(res.len(), 10);
+        assert!(res.iter().all(|(idx, _)| idx % 2 == 1));
+
+        bitpart.compact();
+        assert_eq!(bitpart.len(), remaining.len());
+        assert_eq!(bitpart.get(0).unwrap().distance(&remaining[0]), 0.0);
+
+        test(remaining, bitpart, query, threshold);
     }
 
     #[test]
     fn sisap_nasa() {
-        let nasa = parse_nasa(NASA)
-            .unwrap()
-            .into_iter()
-            .map(Euclidean::new)
-            .collect::<Vec<_>>();
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = builder.build();
+
+        test(nasa, bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_cull_popcnt() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let mut bitpart = builder.clone().build();
 
-        let bitpart = Builder::new(nasa.clone(), 40).build();
-        let query = nasa[317].clone();
-        let threshold = 1.0;
+        bitpart.cull_by_popcnt(0.95);
+        assert!(bitpart.zones() < 40 * 5 + 780);
+
+        // Culling while building leaves the same zones
+        let built = builder.cull_by_popcnt(0.95).build();
+        assert_eq!(built.zones(), bitpart.zones());
+
+        test(nasa, bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_cull_similarity() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+        let builder = builder.ref_points(20);
+
+        let mut bitpart = builder.clone().build();
+
+        bitpart.cull_by_similarity(0.95);
+        assert!(bitpart.zones() < 20 * 5 + 190);
+
+        let built = builder.cull_by_similarity(0.95).build();
+        assert_eq!(built.zones(), bitpart.zones());
+
+        test(nasa, bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_random() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = builder
+            .selection(Selection::Random { seed: 3132448493 })
+            .build();
+
+        test(nasa, bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_farthest_first() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = builder.selection(Selection::FarthestFirst).build();
+
+        test(nasa, bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_radii_per_point() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = builder.radii(Radii::PerPoint).build();
+
+        test(nasa, bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_offset_median() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = builder.offset(Offset::Median).build();
+
+        test(nasa, bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_three_point() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let four_point = builder.clone().build();
+        let three_point = builder.four_point(false).build();
+
+        // Four-point sheets are tighter, so should leave fewer candidates
+        assert!(
+            four_point.candidates(&query, threshold).len()
+                <= three_point.candidates(&query, threshold).len()
+        );
+
+        test(nasa, three_point, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_custom_only() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = (0..NASA_DIMENSION)
+            .fold(builder.generate_exclusions(false), |builder, dim| {
+                builder.exclusion(Dimension { dim, value: 0.0 })
+            })
+            .build();
+        assert_eq!(bitpart.zones(), NASA_DIMENSION);
+
+        test(nasa, bitpart, query, threshold);
+    }
+
+    #[test]
+    fn sisap_nasa_local_custom() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        // Boxed trait objects are neither `Send` nor `Sync`
+        let bitpart = (1..NASA_DIMENSION)
+            .fold(
+                builder
+                    .exclusion(Dimension { dim: 0, value: 0.0 })
+                    .local_exclusion(
+                        Box::new(Dimension { dim: 1, value: 0.0 }) as Box<dyn Exclusion<_>>
+                    ),
+                |builder, dim| builder.local_exclusion(Dimension { dim, value: 0.0 }),
+            )
+            .build();
+        assert_eq!(bitpart.zones(), 40 * 5 + 780 + NASA_DIMENSION + 1);
+
+        test(nasa, bitpart, query, threshold);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn sisap_nasa_serde() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let mut bitpart = builder.build();
+        assert!(bitpart.remove(0));
+        let json = serde_json::to_string(&bitpart).unwrap();
+        let bitpart: Sequential<Euclidean<sisap_data::nasa::Nasa>> =
+            serde_json::from_str(&json).unwrap();
+        assert!(bitpart.get(0).is_none());
+        assert_eq!(bitpart.zones(), 40 * 5 + 780);
+
+        test(nasa[1..].to_vec(), bitpart, query, threshold);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_custom_zones() {
+        let (_, builder, _, _) = nasa_fixture();
+
+        let bitpart = builder.exclusion(Dimension { dim: 0, value: 0.0 }).build();
+        assert!(serde_json::to_string(&bitpart).is_err());
+    }
+
+    #[test]
+    fn sisap_nasa_cull_independent() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+        let builder = builder.ref_points(20);
+
+        let mut bitpart = builder.clone().build();
+
+        bitpart.cull_to_independent(50);
+        assert_eq!(bitpart.zones(), 50);
+
+        let built = builder.cull_to_independent(50).build();
+        assert_eq!(built.zones(), 50);
 
         test(nasa, bitpart, query, threshold);
     }
@@ -189,4 +589,95 @@ mod tests {
 
         test(colors, bitpart, query, threshold);
     }
+
+    #[test]
+    fn sisap_nasa_knn() {
+        let (nasa, builder, query, _) = nasa_fixture();
+
+        let bitpart = builder.build();
+
+        let res = bitpart.knn_search(query.clone(), 10).unwrap();
+
+        // Check results match up with linear search
+        let mut brute_force = nasa
+            .into_iter()
+            .map(|pt| pt.distance(&query))
+            .collect::<Vec<_>>();
+        brute_force.sort_by(f64::total_cmp);
+        brute_force.truncate(10);
+
+        assert_eq!(
+            res.into_iter().map(|(_, d)| d).collect::<Vec<_>>(),
+            brute_force
+        );
+    }
+
+    #[test]
+    fn nearest_neighbour_knn() {
+        let points = parse(&fs::read_to_string("data/100k_d20_flat.ascii").unwrap())
+            .unwrap()
+            .1
+             .1
+            .into_iter()
+            .map(Euclidean::new)
+            .collect::<Vec<_>>();
+
+        let nns: Vec<Vec<(usize, f64)>> =
+            serde_json::from_str(&fs::read_to_string("data/100k_d20_flat.json").unwrap()).unwrap();
+
+        let bitpart = Builder::new(points.clone(), 40).build();
+
+        for (query, nn) in points.iter().cloned().zip(nns.iter()).take(100) {
+            test_knn(&bitpart, query, nn);
+        }
+    }
+
+    #[test]
+    fn sisap_nasa_stats() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = builder.build();
+
+        let (res, stats) = bitpart
+            .range_search_stats(query.clone(), threshold)
+            .unwrap();
+        assert_eq!(
+            res,
+            bitpart
+                .range_search_indices(query.clone(), threshold)
+                .unwrap()
+        );
+
+        assert_eq!(stats.zones, bitpart.zones());
+        assert!(stats.must_be_in + stats.must_be_out <= stats.zones);
+        assert_eq!(stats.results, res.len());
+        assert!(stats.candidates >= stats.results && stats.candidates < nasa.len());
+    }
+
+    #[test]
+    fn sisap_nasa_indices() {
+        let (nasa, builder, query, threshold) = nasa_fixture();
+
+        let bitpart = builder.build();
+
+        let res = bitpart
+            .range_search_indices(query.clone(), threshold)
+            .unwrap();
+
+        // Check indices refer to the same points in the dataset
+        assert!(res
+            .iter()
+            .all(|(idx, d)| bitpart.get(*idx).unwrap().distance(&query) == *d
+                && nasa[*idx].distance(&query) == *d));
+
+        // Check results match up with linear search
+        let brute_force = (0..nasa.len())
+            .filter(|idx| nasa[*idx].distance(&query) <= threshold)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            res.into_iter().map(|(idx, _)| idx).collect::<Vec<_>>(),
+            brute_force
+        );
+    }
 }
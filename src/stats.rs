@@ -0,0 +1,144 @@
+use crate::exclusions::Zone;
+
+use std::{fmt, time::Duration};
+
+/// Results of a range search, as the index of each point and its distance from the query, alongside the [`QueryStats`] of the search.
+pub type RangeStats = (Vec<(usize, f64)>, QueryStats);
+
+/// Statistics recorded by an instrumented range search, returned by [`range_search_stats`](crate::BitPart::range_search_stats).
+///
+/// A range search runs in three phases: the query is tested against every exclusion zone, the bitset columns of zones which
+/// the query [must be in](crate::exclusions::Exclusion::must_be_in) or [must be out](crate::exclusions::Exclusion::must_be_out)
+/// of are combined to filter candidate points, and the distance to every candidate is computed to find the true results.
+///
+/// Few zones which the query must be in or out of, or a high [false positive ratio](QueryStats::false_positive_ratio), suggest that
+/// the exclusion zones partition the dataset poorly for the query's threshold, and that the number of
+/// [reference points](crate::Builder::ref_points) or the [radii](crate::Builder::radii) should be tuned.
+///
+/// The [`Display`](fmt::Display) implementation gives a human-readable summary.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueryStats {
+    /// The number of exclusion zones.
+    pub zones: usize,
+    /// The number of zones which the query must be in.
+    pub must_be_in: usize,
+    /// The number of zones which the query must be out of.
+    pub must_be_out: usize,
+    /// The number of distances computed while testing the query against exclusion zones.
+    /// Custom exclusion zones are not counted, as their cost is unknown.
+    pub exclusion_distances: usize,
+    /// The number of candidate points left after the bitset filter.
+    pub candidates: usize,
+    /// The number of points within the threshold.
+    pub results: usize,
+    /// Time spent testing the query against exclusion zones.
+    pub exclusion_time: Duration,
+    /// Time spent combining bitset columns to find candidates.
+    pub filter_time: Duration,
+    /// Time spent computing the distance to every candidate.
+    pub distance_time: Duration,
+}
+
+impl QueryStats {
+    /// Records the exclusion phase, where the query must be in the zones `ins` and out of the zones `outs`.
+    ///
+    /// A zone is tested with [`must_be_out`](crate::exclusions::Exclusion::must_be_out) only if the query is not known to be in it.
+    pub(crate) fn new<T, E: ?Sized>(
+        zones: &[Zone<T, E>],
+        ins: &[usize],
+        outs: &[usize],
+        exclusion_time: Duration,
+    ) -> Self {
+        let exclusion_distances = zones
+            .iter()
+            .enumerate()
+            .map(|(idx, ez)| {
+                let tests = match ins.binary_search(&idx) {
+                    Ok(_) => 1,
+                    Err(_) => 2,
+                };
+                ez.distances().unwrap_or(0) * tests
+            })
+            .sum();
+
+        Self {
+            zones: zones.len(),
+            must_be_in: ins.len(),
+            must_be_out: outs.len(),
+            exclusion_distances,
+            exclusion_time,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the ratio of candidates which were not within the threshold, or `0.0` if there were no candidates.
+    pub fn false_positive_ratio(&self) -> f64 {
+        match self.candidates {
+            0 => 0.0,
+            candidates => (candidates - self.results) as f64 / candidates as f64,
+        }
+    }
+
+    /// Returns the total time spent on every phase of the query.
+    pub fn total_time(&self) -> Duration {
+        self.exclusion_time + self.filter_time + self.distance_time
+    }
+}
+
+impl fmt::Display for QueryStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "exclusion: {} of {} zones must be in, {} must be out, {} distances ({:?})",
+            self.must_be_in,
+            self.zones,
+            self.must_be_out,
+            self.exclusion_distances,
+            self.exclusion_time
+        )?;
+        writeln!(
+            f,
+            "filter:    {} candidates ({:?})",
+            self.candidates, self.filter_time
+        )?;
+        write!(
+            f,
+            "distance:  {} results, {:.1}% false positives ({:?})",
+            self.results,
+            self.false_positive_ratio() * 100.0,
+            self.distance_time
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exclusions::BallExclusion;
+    use crate::metric::Euclidean;
+
+    #[test]
+    fn exclusion_distances() {
+        let zones = (0..4)
+            .map(|x| Zone::Ball(BallExclusion::new(Euclidean::new([x as f64]), 1.0)))
+            .collect::<Vec<Zone<_>>>();
+
+        // Zones the query must be in are only tested once
+        let stats = QueryStats::new(&zones, &[1], &[0, 3], Duration::ZERO);
+        assert_eq!(stats.exclusion_distances, 1 + 2 * 3);
+        assert_eq!(
+            (stats.zones, stats.must_be_in, stats.must_be_out),
+            (4, 1, 2)
+        );
+    }
+
+    #[test]
+    fn false_positive_ratio() {
+        let mut stats = QueryStats::default();
+        assert_eq!(stats.false_positive_ratio(), 0.0);
+
+        stats.candidates = 8;
+        stats.results = 2;
+        assert_eq!(stats.false_positive_ratio(), 0.75);
+    }
+}
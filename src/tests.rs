@@ -0,0 +1,70 @@
+//! Fixtures shared by the tests of each BitPart variant.
+
+use crate::{metric::Euclidean, Builder};
+use sisap_data::nasa::{parse_nasa, Nasa};
+#[cfg(feature = "disk")]
+use std::{
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+const NASA: &str = include_str!("../sisap-data/src/nasa.ascii");
+
+type NasaPoint = Euclidean<Nasa>;
+
+/// Returns the points of the SISAP `nasa` dataset, a builder over them with 40 reference points, and the query point and
+/// threshold used by the range search tests.
+pub(crate) fn nasa_fixture() -> (Vec<NasaPoint>, Builder<NasaPoint>, NasaPoint, f64) {
+    let nasa = parse_nasa(NASA)
+        .unwrap()
+        .into_iter()
+        .map(Euclidean::new)
+        .collect::<Vec<_>>();
+    let builder = Builder::new(nasa.clone(), 40);
+    let query = nasa[317].clone();
+
+    (nasa, builder, query, 1.0)
+}
+
+/// A path in `/tmp` to build an on-disk index in, which is removed when it is created and again when it is dropped.
+#[cfg(feature = "disk")]
+pub(crate) struct TempPath(PathBuf);
+
+#[cfg(feature = "disk")]
+impl TempPath {
+    pub(crate) fn new(name: &str) -> Self {
+        let path = Self(Path::new("/tmp").join(name));
+        path.remove();
+        path
+    }
+
+    fn remove(&self) {
+        fs::remove_dir_all(&self.0)
+            .or_else(|_| fs::remove_file(&self.0))
+            .ok();
+    }
+}
+
+#[cfg(feature = "disk")]
+impl Deref for TempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(feature = "disk")]
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(feature = "disk")]
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}